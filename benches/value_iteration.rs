@@ -0,0 +1,204 @@
+//! Sweeps of value iteration on large generated worlds, run with `cargo bench`. Double-buffered sweeps
+//! are timed next to the sweeps of the first version of the solver, which cloned the whole world every time.
+
+extern crate markov;
+
+use markov::{Action, Markov, MarkovBuilder, Matrix, Random, State};
+use std::time::{Duration, Instant};
+
+const SWEEPS: u32 = 10;
+const GAMA: f64 = 1.0;
+const COST_OF_MOVE: f64 = -0.04;
+const P1: f64 = 0.8;
+const P2: f64 = 0.1;
+const P3: f64 = 0.1;
+const P4: f64 = 0.0;
+
+/// Square world with every tenth field prohibited, the agent starts in the top left corner
+/// and exits in the bottom right one (reward) or in the top right one (penalty)
+fn generate_world(size: usize, seed: u64) -> Markov {
+    let mut random = Random::new(seed);
+    let mut builder = MarkovBuilder::new();
+    builder.set_size(size, size)
+        .set_state(State::StartState(0.0), 0, 0)
+        .set_state(State::TerminalState(1.0), size - 1, size - 1)
+        .set_state(State::TerminalState(-1.0), size - 1, 0);
+
+    for y in 0..size {
+        for x in 0..size {
+            let corner = (x == 0 || x == size - 1) && (y == 0 || y == size - 1);
+            if !corner && random.below(10) == 0 {
+                builder.set_state(State::ProhibitedState, x, y);
+            }
+        }
+    }
+
+    builder.finalize().unwrap()
+}
+
+/// Field of the first version of the solver, utility was kept in the state itself
+#[derive(Clone)]
+struct Field {
+    state: State,
+    action: Option<Action>
+}
+
+/// Value iteration of the first version of the solver, every sweep cloned the whole world
+/// and wrote updated fields to the clone
+struct ClonedWorld {
+    world: Matrix<Field>
+}
+
+fn left_operation(action: &Action) -> Action {
+    match *action {
+        Action::Up => Action::Left,
+        Action::Left => Action::Down,
+        Action::Down => Action::Right,
+        _ => Action::Up
+    }
+}
+
+fn right_operation(action: &Action) -> Action {
+    match *action {
+        Action::Up => Action::Right,
+        Action::Right => Action::Down,
+        Action::Down => Action::Left,
+        _ => Action::Up
+    }
+}
+
+fn reverse_operation(action: &Action) -> Action {
+    match *action {
+        Action::Up => Action::Down,
+        Action::Left => Action::Right,
+        Action::Down => Action::Up,
+        _ => Action::Left
+    }
+}
+
+fn value(state: &State) -> f64 {
+    match *state {
+        State::StartState(value) | State::NormalState(value) | State::TerminalState(value) | State::SpecialState(value, _) => value,
+        _ => panic!("It should not be able to obtain value from {:?}", state)
+    }
+}
+
+impl ClonedWorld {
+    fn new(markov: &Markov) -> ClonedWorld {
+        let (size_x, size_y) = markov.size();
+        ClonedWorld {
+            world: Matrix::from_fn(size_x, size_y, |x, y| Field { state: markov.state(x, y).unwrap().clone(), action: None })
+        }
+    }
+
+    fn state_after_action(self: &ClonedWorld, action: &Action, x: usize, y: usize) -> &State {
+        let (maybe_x, maybe_y) = match *action {
+            Action::Left => (x.checked_sub(1), Some(y)),
+            Action::Right => (x.checked_add(1), Some(y)),
+            Action::Up => (Some(x), y.checked_sub(1)),
+            _ => (Some(x), y.checked_add(1))
+        };
+
+        let current = &self.world.read_state(x, y).unwrap().state;
+        match (maybe_x, maybe_y) {
+            (Some(new_x), Some(new_y)) => match self.world.read_state(new_x, new_y) {
+                Some(&Field { state: State::ProhibitedState, .. }) | None => current,
+                Some(field) => &field.state
+            },
+            _ => current
+        }
+    }
+
+    fn evaluate_action(self: &ClonedWorld, action: &Action, x: usize, y: usize) -> f64 {
+        let forward_reward = P1*value(self.state_after_action(action, x, y));
+        let left_reward = P2*value(self.state_after_action(&left_operation(action), x, y));
+        let right_reward = P3*value(self.state_after_action(&right_operation(action), x, y));
+        let backward_reward = P4*value(self.state_after_action(&reverse_operation(action), x, y));
+
+        let cost_of_move = match self.world.read_state(x, y).unwrap().state {
+            State::SpecialState(_, cost_of_move) => cost_of_move,
+            _ => COST_OF_MOVE
+        };
+
+        GAMA*(forward_reward + left_reward + right_reward + backward_reward) + cost_of_move
+    }
+
+    fn evaluate_field(self: &ClonedWorld, field: &Field, x: usize, y: usize) -> Field {
+        match field.state {
+            State::TerminalState(_) | State::ProhibitedState => { return field.clone(); },
+            _ => {}
+        }
+
+        let mut action = Action::Down;
+        let mut max = self.evaluate_action(&Action::Down, x, y);
+        for candidate in [Action::Right, Action::Left, Action::Up].iter() {
+            let reward = self.evaluate_action(candidate, x, y);
+            if reward.max(max) == reward {
+                max = reward;
+                action = candidate.clone();
+            }
+        }
+
+        let state = match field.state {
+            State::StartState(_) => State::StartState(max),
+            State::SpecialState(_, cost_of_move) => State::SpecialState(max, cost_of_move),
+            _ => State::NormalState(max)
+        };
+
+        Field { state, action: Some(action) }
+    }
+
+    fn evaluate(self: &mut ClonedWorld) -> f64 {
+        let mut new_world = self.world.clone();
+        let mut error = 0.0;
+
+        for ((x, y), elem) in self.world.cells() {
+            let new_field = self.evaluate_field(elem, x, y);
+            if elem.state != State::ProhibitedState {
+                error += (value(&new_field.state) - value(&elem.state)).abs();
+            }
+            new_world.set_state(new_field, x, y);
+        }
+
+        self.world = new_world;
+
+        error
+    }
+}
+
+/// Average duration of a sweep
+fn time_sweeps<T, F>(solver: &mut T, sweep: F) -> Duration
+    where F: Fn(&mut T) -> f64 {
+    let start = Instant::now();
+    for _ in 0..SWEEPS {
+        sweep(solver);
+    }
+    start.elapsed() / SWEEPS
+}
+
+fn report(size: usize, threads: usize, name: &str, per_sweep: Duration) {
+    println!("{:>4}x{:<4} {} thread(s) {:<15} {:>10.3} ms/sweep {:>8.1} ns/field", size, size, threads, name,
+             per_sweep.as_secs_f64()*1000.0, per_sweep.as_secs_f64()*1e9/(size*size) as f64);
+}
+
+fn main() {
+    for &size in [100, 300, 1000].iter() {
+        let mut markov = generate_world(size, 42);
+        let mut cloned = ClonedWorld::new(&markov);
+        report(size, 1, "cloning world", time_sweeps(&mut cloned, ClonedWorld::evaluate));
+
+        for &threads in [1, 4].iter() {
+            markov = generate_world(size, 42);
+            markov.set_threads(threads);
+            report(size, threads, "double-buffered", time_sweeps(&mut markov, Markov::evaluate));
+        }
+
+        // both solvers made the same sweeps, so they must have found the same utilities and policy
+        for ((x, y), field) in cloned.world.cells() {
+            if let Some(utility) = markov.utility(x, y) {
+                assert!((utility - value(&field.state)).abs() < 1e-9, "utilities of ({}, {}) differ", x, y);
+                assert!(markov.action(x, y) == field.action, "actions of ({}, {}) differ", x, y);
+            }
+        }
+    }
+}
@@ -0,0 +1,347 @@
+use markov::SweepOrder;
+use std::str::FromStr;
+
+pub const USAGE: &str = "Usage: markov [OPTIONS] [INPUT]
+
+Solves grid world described in JSON file INPUT (standard input when omitted or `-`).
+
+Options:
+    -s, --solver SOLVER          value (default), policy, exact, gauss-seidel, prioritized or qlearning
+    -r, --order ORDER            order of gauss-seidel sweeps: rows (default), reverse or terminals
+    -t, --tolerance TOLERANCE    stop when sum of utility changes drops below it (default 0.0001)
+    -m, --max-iterations COUNT   upper bound of iterations (default 10000), of improvement steps for policy,
+                                 of backups per field for prioritized
+    -E, --evaluation-sweeps COUNT
+                                 upper bound of evaluation sweeps per improvement step of policy (default 10000)
+    -f, --format FORMAT          text (default), json or csv (utilities after every iteration)
+    -p, --precision DIGITS       digits after decimal point of utilities in text format (default 3)
+    -j, --threads COUNT          threads sharing every sweep of value and policy iteration (default 1)
+    -a, --learning-rate RATE     learning rate of qlearning, in (0, 1] (default 0.1)
+    -g, --discount GAMA          discount factor of qlearning, in (0, 1] (default gama of the world)
+    -x, --exploration PROBABILITY
+                                 probability of a random move of qlearning, in [0, 1] (default 0.1)
+    -n, --learning-episodes COUNT
+                                 episodes of qlearning (default 10000)
+    -e, --episodes COUNT         simulate COUNT episodes of the solved policy from StartState (default 0, none)
+    -S, --seed SEED              seed of the random number generator of qlearning and the simulation (default 1)
+    -o, --output FILE            write result to FILE instead of standard output
+    -h, --help                   print this help
+
+Options of iterative solvers (--tolerance, --max-iterations, --evaluation-sweeps, --order, --threads) are rejected
+by qlearning, options of qlearning are rejected by the other solvers.
+
+Exit codes:
+    0    success
+    1    invalid command line arguments
+    2    input or output file cannot be read or written
+    3    invalid world description
+    4    solver failed or did not converge within max iterations";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Solver {
+    Value,
+    Policy,
+    Exact,
+    GaussSeidel,
+    Prioritized,
+    QLearning
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Format {
+    Text,
+    Json,
+    Csv
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    pub input: Option<String>,
+    pub output: Option<String>,
+    pub solver: Solver,
+    pub order: SweepOrder,
+    pub format: Format,
+    pub tolerance: f64,
+    pub max_iterations: usize,
+    pub evaluation_sweeps: usize,
+    pub precision: usize,
+    pub threads: usize,
+    pub episodes: usize,
+    pub seed: u64,
+    pub learning_rate: f64,
+    pub discount: Option<f64>,
+    pub exploration: f64,
+    pub learning_episodes: usize
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Help,
+    Run(Options)
+}
+
+impl FromStr for Solver {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Solver, String> {
+        match value {
+            "value" => Ok(Solver::Value),
+            "policy" => Ok(Solver::Policy),
+            "exact" => Ok(Solver::Exact),
+            "gauss-seidel" => Ok(Solver::GaussSeidel),
+            "prioritized" => Ok(Solver::Prioritized),
+            "qlearning" => Ok(Solver::QLearning),
+            _ => Err(format!("Unknown solver: {}", value))
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Format, String> {
+        match value {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            _ => Err(format!("Unknown format: {}", value))
+        }
+    }
+}
+
+/// Parses order of in-place sweeps, FromStr cannot be implemented here for SweepOrder of the library
+fn parse_order(value: &str) -> Result<SweepOrder, String> {
+    match value {
+        "rows" => Ok(SweepOrder::RowMajor),
+        "reverse" => Ok(SweepOrder::Reverse),
+        "terminals" => Ok(SweepOrder::TerminalsOutward),
+        _ => Err(format!("Unknown order: {}", value))
+    }
+}
+
+fn parse_value<T: FromStr>(option: &str, value: Option<&String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("Missing value of {}", option))?;
+    value.parse().map_err(|_| format!("Invalid value of {}: {}", option, value))
+}
+
+/// Parses command line arguments, without the name of the program
+pub fn parse(arguments: &[String]) -> Result<Command, String> {
+    let mut options = Options {
+        input: None,
+        output: None,
+        solver: Solver::Value,
+        order: SweepOrder::RowMajor,
+        format: Format::Text,
+        tolerance: 0.0001,
+        max_iterations: 10000,
+        evaluation_sweeps: 10000,
+        precision: 3,
+        threads: 1,
+        episodes: 0,
+        seed: 1,
+        learning_rate: 0.1,
+        discount: None,
+        exploration: 0.1,
+        learning_episodes: 10000
+    };
+    // long names of the given options which apply only to some solvers
+    let mut given = Vec::new();
+
+    if arguments.iter().any(|argument| argument == "-h" || argument == "--help") {
+        return Ok(Command::Help);
+    }
+
+    let mut arguments = arguments.iter();
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "-s" | "--solver" => {
+                let value = arguments.next().ok_or_else(|| format!("Missing value of {}", argument))?;
+                options.solver = value.parse()?;
+            },
+            "-r" | "--order" => {
+                let value = arguments.next().ok_or_else(|| format!("Missing value of {}", argument))?;
+                options.order = parse_order(value)?;
+                given.push("--order");
+            },
+            "-f" | "--format" => {
+                let value = arguments.next().ok_or_else(|| format!("Missing value of {}", argument))?;
+                options.format = value.parse()?;
+            },
+            "-t" | "--tolerance" => {
+                options.tolerance = parse_value(argument, arguments.next())?;
+                given.push("--tolerance");
+            },
+            "-m" | "--max-iterations" => {
+                options.max_iterations = parse_value(argument, arguments.next())?;
+                given.push("--max-iterations");
+            },
+            "-E" | "--evaluation-sweeps" => {
+                options.evaluation_sweeps = parse_value(argument, arguments.next())?;
+                given.push("--evaluation-sweeps");
+            },
+            "-p" | "--precision" => options.precision = parse_value(argument, arguments.next())?,
+            "-j" | "--threads" => {
+                options.threads = parse_value(argument, arguments.next())?;
+                given.push("--threads");
+            },
+            "-a" | "--learning-rate" => {
+                options.learning_rate = parse_value(argument, arguments.next())?;
+                given.push("--learning-rate");
+            },
+            "-g" | "--discount" => {
+                options.discount = Some(parse_value(argument, arguments.next())?);
+                given.push("--discount");
+            },
+            "-x" | "--exploration" => {
+                options.exploration = parse_value(argument, arguments.next())?;
+                given.push("--exploration");
+            },
+            "-n" | "--learning-episodes" => {
+                options.learning_episodes = parse_value(argument, arguments.next())?;
+                given.push("--learning-episodes");
+            },
+            "-e" | "--episodes" => options.episodes = parse_value(argument, arguments.next())?,
+            "-S" | "--seed" => options.seed = parse_value(argument, arguments.next())?,
+            "-o" | "--output" => options.output = Some(parse_value(argument, arguments.next())?),
+            "-" => options.input = None,
+            _ if argument.starts_with('-') => return Err(format!("Unknown option: {}", argument)),
+            _ if options.input.is_some() => return Err(format!("Unexpected argument: {}", argument)),
+            _ => options.input = Some(argument.clone())
+        }
+    }
+
+    if options.tolerance.is_nan() || options.tolerance < 0.0 {
+        return Err(format!("Invalid value of --tolerance: {}", options.tolerance));
+    }
+    if options.threads == 0 {
+        return Err("Invalid value of --threads: 0".to_string());
+    }
+    if options.evaluation_sweeps == 0 {
+        return Err("Invalid value of --evaluation-sweeps: 0".to_string());
+    }
+    if !(options.learning_rate > 0.0 && options.learning_rate <= 1.0) {
+        return Err(format!("Invalid value of --learning-rate: {}", options.learning_rate));
+    }
+    if let Some(discount) = options.discount {
+        if !(discount > 0.0 && discount <= 1.0) {
+            return Err(format!("Invalid value of --discount: {}", discount));
+        }
+    }
+    if !(0.0..=1.0).contains(&options.exploration) {
+        return Err(format!("Invalid value of --exploration: {}", options.exploration));
+    }
+    if options.learning_episodes == 0 {
+        return Err("Invalid value of --learning-episodes: 0".to_string());
+    }
+
+    let iterative = ["--tolerance", "--max-iterations", "--evaluation-sweeps", "--order", "--threads"];
+    let learning = ["--learning-rate", "--discount", "--exploration", "--learning-episodes"];
+    let inapplicable: &[&str] = if options.solver == Solver::QLearning { &iterative } else { &learning };
+    if let Some(option) = given.iter().find(|option| inapplicable.contains(option)) {
+        let solver = if options.solver == Solver::QLearning { "qlearning" } else { "iterative solvers" };
+        return Err(format!("Option {} does not apply to {}", option, solver));
+    }
+    if options.format == Format::Csv && options.solver != Solver::Value {
+        return Err("CSV output is available only for value iteration".to_string());
+    }
+
+    Ok(Command::Run(options))
+}
+
+#[cfg(test)]
+fn arguments(line: &str) -> Vec<String> {
+    line.split_whitespace().map(String::from).collect()
+}
+
+#[test]
+fn default_options() {
+    assert_eq!(Ok(Command::Run(Options {
+        input: None,
+        output: None,
+        solver: Solver::Value,
+        order: SweepOrder::RowMajor,
+        format: Format::Text,
+        tolerance: 0.0001,
+        max_iterations: 10000,
+        evaluation_sweeps: 10000,
+        precision: 3,
+        threads: 1,
+        episodes: 0,
+        seed: 1,
+        learning_rate: 0.1,
+        discount: None,
+        exploration: 0.1,
+        learning_episodes: 10000
+    })), parse(&[]));
+}
+
+#[test]
+fn parse_all_options() {
+    assert_eq!(Ok(Command::Run(Options {
+        input: Some("world.json".to_string()),
+        output: Some("result.json".to_string()),
+        solver: Solver::Policy,
+        order: SweepOrder::Reverse,
+        format: Format::Json,
+        tolerance: 0.01,
+        max_iterations: 20,
+        evaluation_sweeps: 50,
+        precision: 5,
+        threads: 4,
+        episodes: 100,
+        seed: 7,
+        learning_rate: 0.1,
+        discount: None,
+        exploration: 0.1,
+        learning_episodes: 10000
+    })), parse(&arguments("--solver policy -f json world.json -t 0.01 --max-iterations 20 -o result.json -p 5 -j 4 -r reverse -e 100 -S 7 -E 50")));
+}
+
+#[test]
+fn parse_qlearning_options() {
+    assert_eq!(Ok(Command::Run(Options {
+        input: Some("world.json".to_string()),
+        output: None,
+        solver: Solver::QLearning,
+        order: SweepOrder::RowMajor,
+        format: Format::Text,
+        tolerance: 0.0001,
+        max_iterations: 10000,
+        evaluation_sweeps: 10000,
+        precision: 3,
+        threads: 1,
+        episodes: 50,
+        seed: 3,
+        learning_rate: 0.5,
+        discount: Some(0.9),
+        exploration: 0.0,
+        learning_episodes: 200
+    })), parse(&arguments("-s qlearning world.json -a 0.5 --discount 0.9 -x 0 --learning-episodes 200 -e 50 -S 3")));
+}
+
+#[test]
+fn help_wins_over_other_arguments() {
+    assert_eq!(Ok(Command::Help), parse(&arguments("--solver unknown --help")));
+}
+
+#[test]
+fn reject_invalid_arguments() {
+    assert_eq!(Err("Unknown solver: sarsa".to_string()), parse(&arguments("-s sarsa")));
+    assert_eq!(Err("Unknown order: spiral".to_string()), parse(&arguments("-s gauss-seidel -r spiral")));
+    assert_eq!(Err("Missing value of --tolerance".to_string()), parse(&arguments("--tolerance")));
+    assert_eq!(Err("Invalid value of -m: many".to_string()), parse(&arguments("-m many")));
+    assert_eq!(Err("Unknown option: --verbose".to_string()), parse(&arguments("--verbose")));
+    assert_eq!(Err("Unexpected argument: second.json".to_string()), parse(&arguments("first.json second.json")));
+    assert_eq!(Err("CSV output is available only for value iteration".to_string()), parse(&arguments("-s policy -f csv")));
+    assert_eq!(Err("Invalid value of --threads: 0".to_string()), parse(&arguments("-j 0")));
+    assert_eq!(Err("Invalid value of --evaluation-sweeps: 0".to_string()), parse(&arguments("-E 0")));
+    assert_eq!(Err("Invalid value of --seed: -1".to_string()), parse(&arguments("--seed -1")));
+    assert_eq!(Err("Invalid value of --learning-rate: 0".to_string()), parse(&arguments("-s qlearning -a 0")));
+    assert_eq!(Err("Invalid value of --discount: 1.5".to_string()), parse(&arguments("-s qlearning -g 1.5")));
+    assert_eq!(Err("Invalid value of --exploration: -0.1".to_string()), parse(&arguments("-s qlearning -x -0.1")));
+    assert_eq!(Err("Invalid value of --exploration: NaN".to_string()), parse(&arguments("-s qlearning -x NaN")));
+    assert_eq!(Err("Invalid value of --learning-episodes: 0".to_string()), parse(&arguments("-s qlearning -n 0")));
+    assert_eq!(Err("Option --tolerance does not apply to qlearning".to_string()), parse(&arguments("-t 0.1 -s qlearning")));
+    assert_eq!(Err("Option --max-iterations does not apply to qlearning".to_string()), parse(&arguments("-s qlearning -m 5")));
+    assert_eq!(Err("Option --exploration does not apply to iterative solvers".to_string()), parse(&arguments("-s policy -x 0.2")));
+}
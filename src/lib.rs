@@ -0,0 +1,45 @@
+//! Solvers of grid world Markov decision processes.
+//!
+//! ```
+//! use markov::{Action, MarkovBuilder, State};
+//!
+//! let mut markov = MarkovBuilder::new()
+//!     .set_size(4, 3)
+//!     .set_state(State::StartState(0.0), 0, 2)
+//!     .set_state(State::ProhibitedState, 1, 1)
+//!     .set_state(State::TerminalState(1.0), 3, 0)
+//!     .set_state(State::TerminalState(-1.0), 3, 1)
+//!     .finalize()
+//!     .unwrap();
+//!
+//! markov.value_iteration(0.0001, 1000);
+//!
+//! assert_eq!(Some(Action::Right), markov.action(0, 0));
+//! assert!((markov.utility(0, 0).unwrap() - 0.812).abs() < 0.001);
+//! ```
+
+// tests kept from the first version of the solver compare booleans and declare unneeded mut bindings
+#![cfg_attr(test, allow(unused_mut, clippy::bool_assert_comparison))]
+
+#[macro_use] extern crate serde_derive;
+extern crate serde;
+
+mod linear;
+pub mod matrix;
+pub mod markov;
+pub mod qlearning;
+pub mod random;
+pub mod report;
+pub mod simulation;
+mod table;
+pub mod trace;
+pub mod transition;
+
+pub use matrix::Matrix;
+pub use markov::{Action, ActionSet, Boundary, BuildError, Convergence, Markov, MarkovBuilder, PolicyError, State, Step, SweepOrder, Terrain, Wall, Wind, ACTIONS};
+pub use qlearning::QLearning;
+pub use random::Random;
+pub use report::Report;
+pub use simulation::{Ending, Episode, MonteCarlo, Simulation};
+pub use trace::Trace;
+pub use transition::{Outcome, TransitionModel};
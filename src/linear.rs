@@ -0,0 +1,167 @@
+/// Upper bound of values stored by a `BandMatrix` solvers are allowed to allocate, 128 MiB of f64
+pub const MAX_ENTRIES: usize = 1 << 24;
+
+/// Square matrix with non-zero values only within `lower` columns left and `upper` columns right of the diagonal.
+/// Every row stores a window of `2*lower + upper + 1` columns covering `row - lower ..= row + upper + lower`,
+/// the extra `lower` columns take values moved by row swaps of partial pivoting. Window never exceeds
+/// the whole row, so a band wider than the matrix is stored as a dense matrix.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BandMatrix {
+    size: usize,
+    lower: usize,
+    upper: usize,
+    data: Vec<f64>
+}
+
+impl BandMatrix {
+    pub fn new(size: usize, lower: usize, upper: usize) -> BandMatrix {
+        BandMatrix {
+            size,
+            lower,
+            upper,
+            data: vec![0.0; BandMatrix::entries(size, lower, upper)]
+        }
+    }
+
+    /// Number of values stored by a band matrix, so its size can be checked before it is allocated
+    pub fn entries(size: usize, lower: usize, upper: usize) -> usize {
+        size.saturating_mul(BandMatrix::width(size, lower, upper))
+    }
+
+    fn width(size: usize, lower: usize, upper: usize) -> usize {
+        lower.saturating_mul(2).saturating_add(upper).saturating_add(1).min(size)
+    }
+
+    fn position(self: &BandMatrix, row: usize, column: usize) -> usize {
+        let width = BandMatrix::width(self.size, self.lower, self.upper);
+        // windows of the first and last rows are moved inside of the matrix
+        let first = row.saturating_sub(self.lower).min(self.size - width);
+        assert!(column >= first && column < first + width && column + self.lower >= row, "({}, {}) lies outside of band", row, column);
+        row*width + column - first
+    }
+
+    fn get(self: &BandMatrix, row: usize, column: usize) -> f64 {
+        self.data[self.position(row, column)]
+    }
+
+    fn set(self: &mut BandMatrix, row: usize, column: usize, value: f64) {
+        let position = self.position(row, column);
+        self.data[position] = value;
+    }
+
+    /// Adds `value` at (row, column), panics when it lies outside of the band
+    pub fn add(self: &mut BandMatrix, row: usize, column: usize, value: f64) {
+        let position = self.position(row, column);
+        self.data[position] += value;
+    }
+}
+
+/// Solves `a * x = b` using Gaussian elimination with partial pivoting, in time proportional to
+/// size times both widths of the band. Returns None when the matrix is singular (or numerically close to being singular).
+pub fn solve(mut a: BandMatrix, mut b: Vec<f64>) -> Option<Vec<f64>> {
+    const EPSILON: f64 = 1e-12;
+    let size = b.len();
+    assert_eq!(a.size, size, "matrix and right side of different sizes");
+    let width = a.upper + a.lower;
+    let last_column = |row: usize| (row + width).min(size - 1);
+
+    for column in 0..size {
+        let last_row = (column + a.lower).min(size - 1);
+        let pivot = (column..=last_row)
+            .max_by(|&first, &second| a.get(first, column).abs().partial_cmp(&a.get(second, column).abs()).unwrap())?;
+
+        if a.get(pivot, column).abs() < EPSILON {
+            return None;
+        }
+
+        if pivot != column {
+            for index in column..=last_column(column) {
+                let value = a.get(column, index);
+                a.set(column, index, a.get(pivot, index));
+                a.set(pivot, index, value);
+            }
+            b.swap(column, pivot);
+        }
+
+        for row in column + 1..=last_row {
+            let factor = a.get(row, column) / a.get(column, column);
+            if factor == 0.0 {
+                continue;
+            }
+            for index in column..=last_column(column) {
+                let value = a.get(row, index) - factor * a.get(column, index);
+                a.set(row, index, value);
+            }
+            b[row] -= factor * b[column];
+        }
+    }
+
+    let mut x = vec![0.0; size];
+    for row in (0..size).rev() {
+        let sum: f64 = (row + 1..=last_column(row)).map(|index| a.get(row, index) * x[index]).sum();
+        x[row] = (b[row] - sum) / a.get(row, row);
+    }
+
+    Some(x)
+}
+
+#[cfg(test)]
+fn band_matrix(rows: &[&[f64]], lower: usize, upper: usize) -> BandMatrix {
+    let mut a = BandMatrix::new(rows.len(), lower, upper);
+    for (row, values) in rows.iter().enumerate() {
+        for (column, &value) in values.iter().enumerate() {
+            if value != 0.0 {
+                a.add(row, column, value);
+            }
+        }
+    }
+    a
+}
+
+#[test]
+fn solve_simple_system() {
+    let x = solve(band_matrix(&[&[2.0, 1.0], &[1.0, 3.0]], 1, 1), vec![3.0, 5.0]).unwrap();
+
+    assert!((x[0] - 0.8).abs() < 1e-12);
+    assert!((x[1] - 1.4).abs() < 1e-12);
+}
+
+#[test]
+fn solve_system_requiring_pivoting() {
+    let x = solve(band_matrix(&[&[0.0, 1.0], &[1.0, 0.0]], 1, 1), vec![2.0, 3.0]).unwrap();
+
+    assert_eq!(vec![3.0, 2.0], x);
+}
+
+#[test]
+fn solve_tridiagonal_system_requiring_pivoting() {
+    let rows: &[&[f64]] = &[&[1.0, 2.0, 0.0, 0.0], &[4.0, 1.0, 1.0, 0.0], &[0.0, 1.0, 0.0, 2.0], &[0.0, 0.0, 3.0, 1.0]];
+    let expected = [1.0, -1.0, 2.0, 0.5];
+    let b = rows.iter().map(|row| row.iter().zip(expected.iter()).map(|(value, x)| value*x).sum()).collect();
+
+    let x = solve(band_matrix(rows, 1, 1), b).unwrap();
+
+    for (value, expected) in x.iter().zip(expected.iter()) {
+        assert!((value - expected).abs() < 1e-12);
+    }
+    assert_eq!(4*4, BandMatrix::entries(4, 1, 1));
+}
+
+#[test]
+fn band_wider_than_matrix_is_stored_densely() {
+    let rows: &[&[f64]] = &[&[2.0, 0.0, 1.0], &[0.0, 1.0, 0.0], &[1.0, 0.0, 2.0]];
+
+    let x = solve(band_matrix(rows, 2, 2), vec![4.0, 2.0, 5.0]).unwrap();
+
+    assert!((x[0] - 1.0).abs() < 1e-12 && (x[1] - 2.0).abs() < 1e-12 && (x[2] - 2.0).abs() < 1e-12);
+    assert_eq!(3*3, BandMatrix::entries(3, 2, 2));
+    assert_eq!(100*21, BandMatrix::entries(100, 5, 10));
+    // 50x50 world wrapping along y and 60x60 world with a teleport across it
+    assert!(BandMatrix::entries(50*50, 49*50, 49*50) <= MAX_ENTRIES);
+    assert!(BandMatrix::entries(60*60 - 1, 60*60 - 3, 60*60 - 3) <= MAX_ENTRIES);
+}
+
+#[test]
+fn return_none_for_singular_system() {
+    assert_eq!(None, solve(band_matrix(&[&[1.0, -1.0], &[-1.0, 1.0]], 1, 1), vec![0.0, 0.0]));
+}
@@ -1,24 +1,169 @@
-#[macro_use] extern crate serde_derive;
-extern crate serde;
+extern crate markov;
 extern crate serde_json;
 
-use std::io::{self, Read};
+mod cli;
 
-mod matrix;
-mod markov;
+use cli::{Command, Format, Options, Solver};
+use markov::{Convergence, Ending, Simulation, State};
+use std::env;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::process;
 
-fn main() {
-    let mut buffer = String::new();
-    io::stdin().read_to_string(&mut buffer).expect("Invalid input");
+const USAGE_ERROR: i32 = 1;
+const IO_ERROR: i32 = 2;
+const INVALID_WORLD: i32 = 3;
+const SOLVER_FAILED: i32 = 4;
 
-    let markov_builder: markov::MarkovBuilder = serde_json::from_str(&buffer).expect("Invalid structure of data");
+fn fail(code: i32, message: String) -> ! {
+    eprintln!("{}", message);
+    process::exit(code);
+}
 
-    let mut markov = markov_builder.finalize();
-    let mut number_of_iterations = 0;
+fn read_input(options: &Options) -> io::Result<String> {
+    let mut buffer = String::new();
+    match options.input {
+        Some(ref path) => File::open(path)?.read_to_string(&mut buffer)?,
+        None => io::stdin().read_to_string(&mut buffer)?
+    };
+    Ok(buffer)
+}
 
-    while markov.evaluate() > 0.0001 {
-        number_of_iterations += 1;
+fn open_output(options: &Options) -> io::Result<Box<dyn Write>> {
+    match options.output {
+        Some(ref path) => Ok(Box::new(File::create(path)?)),
+        None => Ok(Box::new(io::stdout()))
     }
+}
 
-    println!("Algorithm finished after {} iterations with result: {:#?}", number_of_iterations, markov);
+/// Summary of simulated episodes appended to text output
+fn write_simulation(output: &mut dyn Write, simulation: &Simulation, precision: usize) -> io::Result<()> {
+    let (low, high) = simulation.confidence_interval;
+    writeln!(output, "Simulated {} episodes from StartState:", simulation.episodes.len())?;
+    writeln!(output, "    discounted return: mean {:.*}, stddev {:.*}, 95% confidence interval [{:.*}, {:.*}]",
+             precision, simulation.mean, precision, simulation.stddev, precision, low, precision, high)?;
+    for &(x, y, rate) in simulation.terminations.iter() {
+        writeln!(output, "    TerminalState ({}, {}): {:.1}%", x, y, 100.0*rate)?;
+    }
+    if simulation.episodes.iter().any(|episode| matches!(episode.ending, Ending::OffEdge(_, _))) {
+        writeln!(output, "    fell off the world: {:.1}%", 100.0*simulation.off_edge)?;
+    }
+    writeln!(output, "    cut after maximal number of moves: {:.1}%", 100.0*simulation.cut)
+}
+
+fn main() {
+    let arguments: Vec<String> = env::args().skip(1).collect();
+    let options = match cli::parse(&arguments) {
+        Ok(Command::Run(options)) => options,
+        Ok(Command::Help) => {
+            println!("{}", cli::USAGE);
+            return;
+        },
+        Err(message) => fail(USAGE_ERROR, format!("{}\n\n{}", message, cli::USAGE))
+    };
+
+    let buffer = read_input(&options)
+        .unwrap_or_else(|error| fail(IO_ERROR, format!("Invalid input: {}", error)));
+
+    let markov_builder: markov::MarkovBuilder = serde_json::from_str(&buffer)
+        .unwrap_or_else(|error| fail(INVALID_WORLD, format!("Invalid structure of data: {}", error)));
+
+    let mut markov = markov_builder.finalize()
+        .unwrap_or_else(|error| fail(INVALID_WORLD, format!("Invalid world: {}", error)));
+
+    markov.set_threads(options.threads);
+    let mut trace = markov::Trace::new();
+
+    let (number_of_iterations, convergence) = match options.solver {
+        Solver::Policy => {
+            let convergence = markov.policy_iteration(options.tolerance, options.max_iterations, options.evaluation_sweeps);
+            (convergence.iterations, Some(convergence))
+        },
+        Solver::Exact => {
+            // value iteration only finds the policy, the reported result is the single exact solve of its utilities
+            markov.value_iteration(options.tolerance, options.max_iterations);
+            let error = markov.evaluate_policy_exactly()
+                .unwrap_or_else(|error| fail(SOLVER_FAILED, error.to_string()));
+            let (size_x, size_y) = markov.size();
+            let backups = (0..size_y)
+                .flat_map(|y| (0..size_x).map(move |x| (x, y)))
+                .filter(|&(x, y)| markov.state(x, y).is_some_and(State::is_occupiable))
+                .count();
+            (1, Some(Convergence { iterations: 1, error, backups, converged: true }))
+        },
+        Solver::QLearning => {
+            let mut q_learning = markov::QLearning::new();
+            q_learning.set_learning_rate(options.learning_rate)
+                .set_exploration(options.exploration)
+                .set_episodes(options.learning_episodes)
+                .set_seed(options.seed);
+            if let Some(discount) = options.discount {
+                q_learning.set_gama(discount);
+            }
+            markov = q_learning.learn(&markov)
+                .unwrap_or_else(|| fail(SOLVER_FAILED, "Q-learning requires StartState".to_string()));
+            (q_learning.episodes(), None)
+        },
+        Solver::Value => {
+            // utilities of every iteration are kept only when they are written out
+            let convergence = if options.format == Format::Csv {
+                markov.value_iteration_traced(options.tolerance, options.max_iterations, &mut trace)
+            } else {
+                markov.value_iteration(options.tolerance, options.max_iterations)
+            };
+            (convergence.iterations, Some(convergence))
+        },
+        Solver::GaussSeidel => {
+            let convergence = markov.gauss_seidel(options.tolerance, options.max_iterations, options.order);
+            (convergence.iterations, Some(convergence))
+        },
+        Solver::Prioritized => {
+            // iterations of prioritized sweeping are single backups, so the limit is scaled to whole sweeps
+            let (size_x, size_y) = markov.size();
+            let convergence = markov.prioritized_sweeping(options.tolerance, options.max_iterations.saturating_mul(size_x*size_y));
+            (convergence.iterations, Some(convergence))
+        }
+    };
+    let error = convergence.map(|convergence: Convergence| convergence.error);
+    let backups = convergence.map(|convergence| convergence.backups);
+
+    let simulation = if options.episodes > 0 {
+        let simulation = markov::MonteCarlo::new()
+            .set_episodes(options.episodes)
+            .set_seed(options.seed)
+            .simulate(&markov)
+            .unwrap_or_else(|error| fail(SOLVER_FAILED, format!("Simulation failed: {}", error)));
+        Some(simulation)
+    } else {
+        None
+    };
+
+    let mut output = open_output(&options)
+        .unwrap_or_else(|error| fail(IO_ERROR, format!("Invalid output: {}", error)));
+
+    let written = match options.format {
+        Format::Csv => trace.write_csv(&mut output),
+        Format::Json => {
+            let mut report = markov::Report::new(&markov, number_of_iterations, error, backups);
+            report.simulation = simulation;
+            serde_json::to_writer_pretty(&mut output, &report).map_err(io::Error::from)
+                .and_then(|_| writeln!(output))
+        },
+        Format::Text => match backups {
+            Some(backups) => write!(output, "Algorithm finished after {} iterations ({} backups) with result:\n{:.*}",
+                                    number_of_iterations, backups, options.precision, markov),
+            None => write!(output, "Algorithm finished after {} iterations with result:\n{:.*}",
+                           number_of_iterations, options.precision, markov)
+        }.and_then(|_| match simulation {
+            Some(ref simulation) => write_simulation(&mut output, simulation, options.precision),
+            None => Ok(())
+        })
+    };
+    written.unwrap_or_else(|error| fail(IO_ERROR, format!("Invalid output: {}", error)));
+
+    if let Some(convergence) = convergence {
+        if !convergence.converged {
+            fail(SOLVER_FAILED, format!("Solver did not converge within {} iterations, error: {}", options.max_iterations, convergence.error));
+        }
+    }
 }
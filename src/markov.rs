@@ -1,61 +1,415 @@
+use linear;
 use matrix;
+use random::Random;
+use trace::Trace;
+use transition::{self, Outcome, TransitionModel};
+use table::{Target, TransitionTable};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::error;
 use std::fmt;
+use std::mem;
+use std::thread;
 
+/// Single cell of the world, its definition (state, immediate reward and terrain)
 #[derive(Clone, PartialEq)]
 pub struct Field {
     state: State,
-    action: Option<Action>
+    reward: f64,
+    terrain: Option<usize>
 }
 
-#[derive(Debug, Clone, PartialEq)]
+/// Side between two fields (from, to) crossed in that direction
+type Edge = ((usize, usize), (usize, usize));
+
+/// Grid world Markov decision process together with current utilities and policy.
+#[derive(Debug, Clone)]
 pub struct Markov {
     world: matrix::Matrix<Field>,
+    utilities: matrix::Matrix<f64>,
+    /// Spare buffer a sweep writes updated utilities to, then it is swapped with `utilities`
+    next_utilities: matrix::Matrix<f64>,
+    policy: matrix::Matrix<Option<Action>>,
+    /// Transitions of every field and action compiled from the fields below, read by the solvers
+    table: TransitionTable,
+    /// Number of threads sharing rows of the world in every sweep
+    threads: usize,
     gama: f64,
-    cost_of_move: f64,
-    p1: f64,
-    p2: f64,
-    p3: f64,
-    p4: f64
+    transition_model: TransitionModel,
+    terrains: Vec<Terrain>,
+    actions: ActionSet,
+    /// Edges (from, to) which cannot be crossed in that direction, with cost of bumping into them
+    blocked_edges: HashMap<Edge, f64>,
+    cost_of_bump: f64,
+    boundary: (Boundary, Boundary),
+    start: Option<(usize, usize)>,
+    /// Wind of every column and every row, as distribution of (probability, shift in cells)
+    column_winds: Vec<Option<Vec<(f64, isize)>>>,
+    row_winds: Vec<Option<Vec<(f64, isize)>>>
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Description of a world, deserialized from JSON input or assembled programmatically.
+/// Fields not mentioned in `states` are `NormalState(0.0)`, fields not mentioned in `rewards`
+/// have reward `cost_of_move` (own cost of move for `SpecialState`, value for `TerminalState`).
+/// Moves follow `transitions` when given, otherwise textbook model built from `p1`, `p2` and `p3`.
+/// Agent chooses from `actions`, four neighbours by default, and cannot cross `walls` between fields.
+/// Move bumping back from a wall, prohibited field or edge of the world costs additional `cost_of_bump`.
+/// Edges of the world along x and y axis behave according to `boundary`, walls by default.
+/// After every move `winds` of the column and row of the left field push the agent further.
+/// Fields listed in `floors` are covered by one of `terrains`, which has its own reward and slip model.
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct MarkovBuilder {
     x: usize,
     y: usize,
     states: Vec<(State, usize, usize)>,
+    #[serde(default)]
+    rewards: Vec<(f64, usize, usize)>,
     gama: f64,
     cost_of_move: f64,
+    #[serde(default = "default_p1")]
     p1: f64,
+    #[serde(default = "default_p2")]
     p2: f64,
-    p3: f64
+    #[serde(default = "default_p3")]
+    p3: f64,
+    #[serde(default)]
+    transitions: Option<TransitionModel>,
+    #[serde(default)]
+    terrains: Vec<Terrain>,
+    #[serde(default)]
+    floors: Vec<(String, usize, usize)>,
+    #[serde(default)]
+    actions: ActionSet,
+    #[serde(default)]
+    walls: Vec<Wall>,
+    #[serde(default)]
+    cost_of_bump: f64,
+    #[serde(default)]
+    boundary: (Boundary, Boundary),
+    #[serde(default)]
+    winds: Vec<Wind>
+}
+
+/// Thin wall on the edge between two neighbouring fields (sharing a side), a one-way wall
+/// lets the agent pass from `from` to `to`, but not back. Bumping into it costs its own
+/// `cost_of_bump` instead of the one of the world.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Wall {
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+    #[serde(default)]
+    pub one_way: bool,
+    #[serde(default)]
+    pub cost_of_bump: Option<f64>
+}
+
+/// Kind of floor (ice, mud, carpet...), moves from its fields follow `transitions`
+/// and its reward replaces `cost_of_move`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Terrain {
+    pub name: String,
+    pub reward: f64,
+    pub transitions: TransitionModel
 }
 
+/// Kind of a field of the world, with the initial utility of fields the agent chooses an action in
+#[allow(clippy::enum_variant_names)]
 #[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub enum State {
+    /// Wall, agent bumps back when trying to enter it
     ProhibitedState,
+    /// Field where simulated episodes start, with its initial utility
     StartState(f64),
+    /// Field ending the episode, its utility is fixed and equal to its reward
     TerminalState(f64),
+    /// Field with initial utility and its own cost of move
     SpecialState(f64, f64),
-    NormalState(f64)
+    /// Ordinary field with its initial utility
+    NormalState(f64),
+    /// Agent entering it is immediately moved to one of the targets (probability, x, y)
+    TeleportState(Vec<(f64, usize, usize)>),
+    /// Agent entering it receives its reward and is sent back to the StartState, the episode goes on
+    CliffState(f64)
 }
 
-#[derive(Clone, PartialEq)]
+/// Intended direction of a move, `Up` decreases and `Down` increases the y coordinate
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub enum Action {
     Up,
     Down,
     Left,
-    Right
+    Right,
+    /// Diagonal moves, available only in eight-neighbour worlds
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+    /// Wait in place, the agent never slips when staying
+    Stay
+}
+
+/// All actions of four-neighbour worlds, in order of preference when they are equally good
+pub static ACTIONS: [Action; 4] = [Action::Up, Action::Left, Action::Right, Action::Down];
+
+static ACTIONS_AND_STAY: [Action; 5] = [Action::Up, Action::Left, Action::Right, Action::Down, Action::Stay];
+
+static EIGHT_ACTIONS: [Action; 8] = [Action::Up, Action::Left, Action::Right, Action::Down,
+                                     Action::UpLeft, Action::UpRight, Action::DownLeft, Action::DownRight];
+
+static EIGHT_ACTIONS_AND_STAY: [Action; 9] = [Action::Up, Action::Left, Action::Right, Action::Down,
+                                              Action::UpLeft, Action::UpRight, Action::DownLeft, Action::DownRight, Action::Stay];
+
+/// Actions available in a world. Slips of the textbook model (`p2`, `p3`) turn the agent by 90 degrees
+/// in four-neighbour worlds and by 45 degrees in eight-neighbour worlds.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum ActionSet {
+    #[default]
+    FourNeighbours,
+    FourNeighboursAndStay,
+    EightNeighbours,
+    EightNeighboursAndStay
+}
+
+/// Behaviour of the edges of the world along one axis
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum Boundary {
+    /// Agent bumps back from the edge
+    #[default]
+    Wall,
+    /// Agent leaving the world appears on the opposite edge
+    Wrap,
+    /// Agent falls off the edge, which ends the episode like a TerminalState with the given value
+    Absorbing(f64)
+}
+
+/// Wind blowing in a column (along y axis, negative shift blows upwards) or in a row (along x axis,
+/// negative shift blows to the left), as distribution of (probability, shift in cells). Agent is pushed
+/// cell by cell until the shift is done or a wall stops it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Wind {
+    Column(usize, Vec<(f64, isize)>),
+    Row(usize, Vec<(f64, isize)>)
+}
+
+/// Where a single outcome of an action leads
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Destination {
+    /// Field of the world, the left one when the agent bumped back
+    Field(usize, usize),
+    /// Off an absorbing edge, with value of the edge
+    OffEdge(f64)
+}
+
+/// Summary of a solver run, `backups` counts updates of utilities of single fields. `converged` is false
+/// when the solver stopped after its maximal number of iterations.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct Convergence {
+    /// Number of iterations, a sweep, an improvement step or a single backup depending on the solver
+    pub iterations: usize,
+    /// Sum of absolute changes of utilities in the last iteration
+    pub error: f64,
+    pub backups: usize,
+    pub converged: bool
+}
+
+/// Order in which in-place sweeps of `Markov::gauss_seidel` visit the fields
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SweepOrder {
+    /// Row by row from the top left field
+    RowMajor,
+    /// Row by row backwards from the bottom right field
+    Reverse,
+    /// Breadth first from TerminalStates and fields next to absorbing edges against the direction of moves,
+    /// fields which never get to any of them come last
+    TerminalsOutward
+}
+
+/// Field waiting for its backup in prioritized sweeping, larger Bellman error first and lower index on ties
+#[derive(Debug, PartialEq)]
+struct Priority(f64, usize);
+
+impl Eq for Priority {}
+
+impl PartialOrd for Priority {
+    fn partial_cmp(&self, other: &Priority) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Priority {
+    fn cmp(&self, other: &Priority) -> Ordering {
+        self.0.total_cmp(&other.0).then(other.1.cmp(&self.1))
+    }
+}
+
+/// Outcome of a single sampled move of the agent
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    /// Field the agent ended in
+    pub x: usize,
+    pub y: usize,
+    /// Reward collected by the move
+    pub reward: f64,
+    /// Value of the TerminalState or absorbing edge which ended the episode
+    pub terminal: Option<f64>
+}
+
+/// Reasons why a policy cannot be evaluated exactly or simulated
+#[derive(Debug, Clone, PartialEq)]
+pub enum PolicyError {
+    MissingAction(usize, usize),
+    SingularSystem,
+    /// Linear system would need this many values even in its smaller storage, band or dense, more than `linear::MAX_ENTRIES`
+    SystemTooLarge(usize),
+    MissingStartState,
+    NoEpisodes
+}
+
+impl fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PolicyError::MissingAction(x, y) => write!(f, "Field ({}, {}) has no action assigned", x, y),
+            PolicyError::SingularSystem => write!(f, "Policy cannot be evaluated, it never reaches TerminalState from some field"),
+            PolicyError::SystemTooLarge(entries) =>
+                write!(f, "Policy cannot be evaluated exactly, its linear system needs {} values, at most {} are allowed", entries, linear::MAX_ENTRIES),
+            PolicyError::MissingStartState => write!(f, "Policy cannot be simulated, world has no StartState"),
+            PolicyError::NoEpisodes => write!(f, "Policy cannot be simulated in zero episodes")
+        }
+    }
 }
 
+/// Reasons why `MarkovBuilder::finalize` rejects a world, entries of `states` are referred to by their index
+#[derive(Debug, Clone, PartialEq)]
+pub enum BuildError {
+    EmptyWorld(usize, usize),
+    OutOfRange { entry: usize, x: usize, y: usize },
+    DuplicateField { entry: usize, previous_entry: usize, x: usize, y: usize },
+    RewardOutOfRange { entry: usize, x: usize, y: usize },
+    DuplicateReward { entry: usize, previous_entry: usize, x: usize, y: usize },
+    ProbabilityOutOfRange(&'static str, f64),
+    ProbabilitiesExceedOne(f64),
+    InvalidDistribution(String),
+    DuplicateTerrain { entry: usize, previous_entry: usize },
+    UnknownTerrain { entry: usize, name: String },
+    FloorOutOfRange { entry: usize, x: usize, y: usize },
+    DuplicateFloor { entry: usize, previous_entry: usize, x: usize, y: usize },
+    TeleportOutOfRange { entry: usize, x: usize, y: usize },
+    TeleportToInvalidField { entry: usize, x: usize, y: usize },
+    CliffWithoutStart(usize),
+    WindOutOfRange(usize),
+    DuplicateWind { entry: usize, previous_entry: usize },
+    WallOutOfRange { entry: usize, x: usize, y: usize },
+    WallBetweenDistantFields(usize),
+    GamaOutOfRange(f64),
+    NoTerminalState
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BuildError::EmptyWorld(x, y) =>
+                write!(f, "World of size x = {}, y = {} has no fields", x, y),
+            BuildError::OutOfRange { entry, x, y } =>
+                write!(f, "states[{}] at ({}, {}) lies outside of the world", entry, x, y),
+            BuildError::DuplicateField { entry, previous_entry, x, y } =>
+                write!(f, "states[{}] at ({}, {}) overwrites states[{}]", entry, x, y, previous_entry),
+            BuildError::RewardOutOfRange { entry, x, y } =>
+                write!(f, "rewards[{}] at ({}, {}) lies outside of the world", entry, x, y),
+            BuildError::DuplicateReward { entry, previous_entry, x, y } =>
+                write!(f, "rewards[{}] at ({}, {}) overwrites rewards[{}]", entry, x, y, previous_entry),
+            BuildError::ProbabilityOutOfRange(name, value) =>
+                write!(f, "{} = {} is not a probability from range [0, 1]", name, value),
+            BuildError::ProbabilitiesExceedOne(sum) =>
+                write!(f, "p1 + p2 + p3 = {} exceeds 1", sum),
+            BuildError::InvalidDistribution(ref entry) =>
+                write!(f, "{} has probabilities outside of range [0, 1] or not summing up to 1", entry),
+            BuildError::DuplicateTerrain { entry, previous_entry } =>
+                write!(f, "terrains[{}] has the same name as terrains[{}]", entry, previous_entry),
+            BuildError::UnknownTerrain { entry, ref name } =>
+                write!(f, "floors[{}] refers to unknown terrain {}", entry, name),
+            BuildError::FloorOutOfRange { entry, x, y } =>
+                write!(f, "floors[{}] at ({}, {}) lies outside of the world", entry, x, y),
+            BuildError::DuplicateFloor { entry, previous_entry, x, y } =>
+                write!(f, "floors[{}] at ({}, {}) overwrites floors[{}]", entry, x, y, previous_entry),
+            BuildError::TeleportOutOfRange { entry, x, y } =>
+                write!(f, "states[{}] teleports to ({}, {}) outside of the world", entry, x, y),
+            BuildError::TeleportToInvalidField { entry, x, y } =>
+                write!(f, "states[{}] teleports to ({}, {}), where the agent cannot stay", entry, x, y),
+            BuildError::CliffWithoutStart(entry) =>
+                write!(f, "states[{}] is a CliffState, but states contain no StartState to send the agent back to", entry),
+            BuildError::WindOutOfRange(entry) =>
+                write!(f, "winds[{}] blows outside of the world", entry),
+            BuildError::DuplicateWind { entry, previous_entry } =>
+                write!(f, "winds[{}] blows in the same column or row as winds[{}]", entry, previous_entry),
+            BuildError::WallOutOfRange { entry, x, y } =>
+                write!(f, "walls[{}] at ({}, {}) lies outside of the world", entry, x, y),
+            BuildError::WallBetweenDistantFields(entry) =>
+                write!(f, "walls[{}] does not separate fields sharing a side", entry),
+            BuildError::GamaOutOfRange(value) =>
+                write!(f, "gama = {} is outside of range (0, 1]", value),
+            BuildError::NoTerminalState =>
+                write!(f, "states contain no TerminalState, which is required when gama = 1")
+        }
+    }
+}
+
+impl error::Error for BuildError {}
+
+impl error::Error for PolicyError {}
+
 impl fmt::Debug for State {
     // from specification http://sequoia.ict.pwr.wroc.pl/~witold/ai/MDPRL_assignment.html
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            &State::ProhibitedState => write!(f, "F"),
-            &State::StartState(value) => write!(f, "S({:.3})", value),
-            &State::TerminalState(value) => write!(f, "T({:.3})", value),
-            &State::SpecialState(value, _) => write!(f, "B({:.3})", value),
-            &State::NormalState(value) => write!(f, "N({:.3})", value),
+        match *self {
+            State::ProhibitedState => write!(f, "F"),
+            State::StartState(value) => write!(f, "S({:.3})", value),
+            State::TerminalState(value) => write!(f, "T({:.3})", value),
+            State::SpecialState(value, _) => write!(f, "B({:.3})", value),
+            State::NormalState(value) => write!(f, "N({:.3})", value),
+            State::TeleportState(_) => write!(f, "P"),
+            State::CliffState(value) => write!(f, "C({:.3})", value)
+        }
+    }
+}
+
+impl Action {
+    /// Displacement (dx, dy) of the intended move
+    pub fn direction(&self) -> (isize, isize) {
+        match *self {
+            Action::Up => (0, -1),
+            Action::Down => (0, 1),
+            Action::Left => (-1, 0),
+            Action::Right => (1, 0),
+            Action::UpLeft => (-1, -1),
+            Action::UpRight => (1, -1),
+            Action::DownLeft => (-1, 1),
+            Action::DownRight => (1, 1),
+            Action::Stay => (0, 0)
+        }
+    }
+
+    /// Action moving by (dx, dy), None when no action moves that way
+    pub fn from_direction(direction: (isize, isize)) -> Option<Action> {
+        EIGHT_ACTIONS_AND_STAY.iter().find(|action| action.direction() == direction).cloned()
+    }
+}
+
+impl ActionSet {
+    /// Available actions, in order of preference when they are equally good
+    pub fn actions(&self) -> &'static [Action] {
+        match *self {
+            ActionSet::FourNeighbours => &ACTIONS,
+            ActionSet::FourNeighboursAndStay => &ACTIONS_AND_STAY,
+            ActionSet::EightNeighbours => &EIGHT_ACTIONS,
+            ActionSet::EightNeighboursAndStay => &EIGHT_ACTIONS_AND_STAY
+        }
+    }
+
+    /// Outcomes of slipping to the left and to the right of the intended direction
+    pub fn slips(&self) -> (Outcome, Outcome) {
+        match *self {
+            ActionSet::FourNeighbours | ActionSet::FourNeighboursAndStay => (Outcome::Left, Outcome::Right),
+            ActionSet::EightNeighbours | ActionSet::EightNeighboursAndStay => (Outcome::ForwardLeft, Outcome::ForwardRight)
         }
     }
 }
@@ -63,37 +417,170 @@ impl fmt::Debug for State {
 impl fmt::Debug for Action {
     // from specification http://sequoia.ict.pwr.wroc.pl/~witold/ai/MDPRL_assignment.html
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            &Action::Up => write!(f, "^"),
-            &Action::Left => write!(f, "<"),
-            &Action::Right => write!(f, ">"),
-            &Action::Down => write!(f, "v")
+        match *self {
+            Action::Up => write!(f, "^"),
+            Action::Left => write!(f, "<"),
+            Action::Right => write!(f, ">"),
+            Action::Down => write!(f, "v"),
+            Action::UpLeft => write!(f, "\u{2196}"),
+            Action::UpRight => write!(f, "\u{2197}"),
+            Action::DownLeft => write!(f, "\u{2199}"),
+            Action::DownRight => write!(f, "\u{2198}"),
+            Action::Stay => write!(f, "o")
+        }
+    }
+}
+
+impl State {
+    fn initial_utility(&self) -> f64 {
+        match *self {
+            State::ProhibitedState | State::TeleportState(_) | State::CliffState(_) => 0.0,
+            State::StartState(value) | State::TerminalState(value) | State::SpecialState(value, _) | State::NormalState(value) => value
         }
     }
+
+    /// Whether the agent can be in the field, it never is in prohibited, teleport and cliff fields
+    pub fn is_occupiable(&self) -> bool {
+        !matches!(*self, State::ProhibitedState | State::TeleportState(_) | State::CliffState(_))
+    }
+
+    /// Whether the agent chooses an action in the field, so solvers update its utility
+    fn chooses_action(&self) -> bool {
+        self.is_occupiable() && !matches!(*self, State::TerminalState(_))
+    }
+
+    /// Whether entering the field is rewarded, which is the case for all fields the agent can be in and cliffs
+    fn has_reward(&self) -> bool {
+        self.is_occupiable() || matches!(*self, State::CliffState(_))
+    }
+}
+
+impl Wall {
+    /// Wall blocking moves in both directions
+    pub fn new(from: (usize, usize), to: (usize, usize)) -> Wall {
+        Wall { from, to, one_way: false, cost_of_bump: None }
+    }
+
+    /// Passage which can be crossed only from `from` to `to`
+    pub fn one_way(from: (usize, usize), to: (usize, usize)) -> Wall {
+        Wall { from, to, one_way: true, cost_of_bump: None }
+    }
+
+    pub fn set_cost_of_bump(&mut self, cost_of_bump: f64) -> &mut Wall {
+        self.cost_of_bump = Some(cost_of_bump);
+        self
+    }
+}
+
+impl Terrain {
+    pub fn new(name: &str, reward: f64, transitions: TransitionModel) -> Terrain {
+        Terrain { name: name.to_string(), reward, transitions }
+    }
 }
 
 impl Field {
-    pub fn new(state: State) -> Field {
+    /// Field with utility taken from the state, `cost_of_move` is the reward of fields which do not define their own
+    pub fn new(state: State, cost_of_move: f64) -> Field {
+        let reward = match state {
+            State::SpecialState(_, cost_of_move) => cost_of_move,
+            State::TerminalState(value) | State::CliffState(value) => value,
+            _ => cost_of_move
+        };
         Field {
-            state: state,
-            action: None
+            state,
+            reward,
+            terrain: None
+        }
+    }
+
+    /// Utility before solving, the one of a terminal field is its reward
+    fn initial_utility(&self) -> f64 {
+        match self.state {
+            State::TerminalState(_) => self.reward,
+            _ => self.state.initial_utility()
         }
     }
 }
 
 impl fmt::Debug for Field {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match &self.action {
-            &Some(ref value) => write!(f, "{:?}, {:?}", self.state, self.action.clone().unwrap()),
-            &None => write!(f, "{:?}, {:?}", self.state, self.action)
+        match self.state {
+            State::ProhibitedState => write!(f, "F"),
+            State::StartState(_) => write!(f, "S({:.3})", self.reward),
+            State::TerminalState(_) => write!(f, "T({:.3})", self.reward),
+            State::SpecialState(_, _) => write!(f, "B({:.3})", self.reward),
+            State::NormalState(_) => write!(f, "N({:.3})", self.reward),
+            State::TeleportState(_) => write!(f, "P"),
+            State::CliffState(_) => write!(f, "C({:.3})", self.reward)
         }
     }
 }
 
+impl PartialEq for Markov {
+    /// Content of the spare buffer of utilities depends only on the history of sweeps, the transition table
+    /// is compiled from the other fields and the number of threads does not change results, so none is compared
+    fn eq(&self, other: &Markov) -> bool {
+        self.world == other.world && self.utilities == other.utilities && self.policy == other.policy
+            && self.gama == other.gama && self.transition_model == other.transition_model
+            && self.terrains == other.terrains && self.actions == other.actions
+            && self.blocked_edges == other.blocked_edges && self.cost_of_bump == other.cost_of_bump
+            && self.boundary == other.boundary && self.start == other.start
+            && self.column_winds == other.column_winds && self.row_winds == other.row_winds
+    }
+}
+
+impl fmt::Display for Markov {
+    /// Table of utilities followed by map of the policy, precision of utilities defaults to 3 digits
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let precision = f.precision().unwrap_or(3);
+
+        let utilities = self.world.zip_with(&self.utilities, |field, utility| match field.state {
+            State::ProhibitedState => "#".to_string(),
+            State::TeleportState(_) => "@".to_string(),
+            State::CliffState(_) => "C".to_string(),
+            _ => format!("{:.*}", precision, utility)
+        });
+        let width = utilities.as_slice().iter().map(String::len).max().unwrap_or(0);
+
+        writeln!(f, "Utilities:")?;
+        for row in utilities.rows() {
+            for utility in row.iter() {
+                write!(f, " {:>width$}", utility, width = width)?;
+            }
+            writeln!(f)?;
+        }
+
+        writeln!(f, "Policy:")?;
+        for (row, actions) in self.world.rows().zip(self.policy.rows()) {
+            for (field, action) in row.iter().zip(actions.iter()) {
+                let marker = match field.state {
+                    State::StartState(_) => "S",
+                    State::SpecialState(_, _) => "B",
+                    _ => " "
+                };
+                let direction = match (&field.state, action) {
+                    (&State::ProhibitedState, _) => "#".to_string(),
+                    (&State::TeleportState(_), _) => "@".to_string(),
+                    (&State::CliffState(_), _) => "C".to_string(),
+                    (&State::TerminalState(_), _) => "T".to_string(),
+                    (_, Some(action)) => format!("{:?}", action),
+                    (_, None) => ".".to_string()
+                };
+                write!(f, " {}{}", marker, direction)?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
 trait Roundable {
     fn round_to(&self, precision: i32) -> f64;
 }
 
+#[cfg(test)]
 impl Roundable for f64 {
     fn round_to(&self, precision: i32) -> f64 {
         let divisor = 10_f64.powi(precision);
@@ -101,216 +588,1058 @@ impl Roundable for f64 {
     }
 }
 
+fn default_p1() -> f64 { 0.8 }
+fn default_p2() -> f64 { 0.1 }
+fn default_p3() -> f64 { 0.1 }
+
+impl Default for MarkovBuilder {
+    fn default() -> MarkovBuilder {
+        MarkovBuilder::new()
+    }
+}
+
 impl MarkovBuilder {
+    /// World 4x3 with textbook parameters: gama 1.0, cost of move -0.04 and probabilities 0.8/0.1/0.1
     pub fn new() -> MarkovBuilder {
         MarkovBuilder {
             x: 4,
             y: 3,
             states: Vec::new(),
+            rewards: Vec::new(),
             gama: 1.0,
             cost_of_move: -0.04,
-            p1: 0.8,
-            p2: 0.1,
-            p3: 0.1
+            p1: default_p1(),
+            p2: default_p2(),
+            p3: default_p3(),
+            transitions: None,
+            terrains: Vec::new(),
+            floors: Vec::new(),
+            actions: ActionSet::FourNeighbours,
+            walls: Vec::new(),
+            cost_of_bump: 0.0,
+            boundary: (Boundary::Wall, Boundary::Wall),
+            winds: Vec::new()
         }
     }
-    pub fn finalize(&self) -> Markov {
-        let mut matrix = matrix::Matrix::new(Field::new(State::NormalState(0.0)), self.x, self.y);
-        for &(ref state, x, y) in self.states.iter() {
-            matrix.set_state(Field::new(state.clone()), x, y);
+    /// Validates the description and builds the world with zero utilities and no policy,
+    /// see `BuildError` for the reasons of rejecting it
+    pub fn finalize(&self) -> Result<Markov, BuildError> {
+        if self.x == 0 || self.y == 0 {
+            return Err(BuildError::EmptyWorld(self.x, self.y));
+        }
+        let transition_model = match self.transitions {
+            Some(ref transition_model) => {
+                transition_model.validate("transitions")?;
+                transition_model.clone()
+            },
+            None => {
+                for &(name, value) in [("p1", self.p1), ("p2", self.p2), ("p3", self.p3)].iter() {
+                    if !(0.0..=1.0).contains(&value) {
+                        return Err(BuildError::ProbabilityOutOfRange(name, value));
+                    }
+                }
+                if self.p1 + self.p2 + self.p3 > 1.0 + transition::EPSILON {
+                    return Err(BuildError::ProbabilitiesExceedOne(self.p1 + self.p2 + self.p3));
+                }
+                let (left, right) = self.actions.slips();
+                TransitionModel::from_slips(self.p1, self.p2, self.p3, left, right)
+            }
+        };
+        if !(self.gama > 0.0 && self.gama <= 1.0) {
+            return Err(BuildError::GamaOutOfRange(self.gama));
+        }
+        let has_absorbing_edge = matches!(self.boundary, (Boundary::Absorbing(_), _) | (_, Boundary::Absorbing(_)));
+        if self.gama == 1.0 && !has_absorbing_edge
+            && !self.states.iter().any(|(state, _, _)| matches!(*state, State::TerminalState(_))) {
+            return Err(BuildError::NoTerminalState);
+        }
+
+        let mut matrix = matrix::Matrix::new(Field::new(State::NormalState(0.0), self.cost_of_move), self.x, self.y);
+        let mut occupied = HashSet::new();
+        for (entry, &(ref state, x, y)) in self.states.iter().enumerate() {
+            if !matrix.set_state(Field::new(state.clone(), self.cost_of_move), x, y) {
+                return Err(BuildError::OutOfRange { entry, x, y });
+            }
+            if !occupied.insert((x, y)) {
+                let previous_entry = self.states.iter().position(|&(_, previous_x, previous_y)| (previous_x, previous_y) == (x, y)).unwrap();
+                return Err(BuildError::DuplicateField { entry, previous_entry, x, y });
+            }
+        }
+
+        for (entry, (state, _, _)) in self.states.iter().enumerate() {
+            if let State::TeleportState(ref targets) = *state {
+                if !transition::is_distribution(targets.iter().map(|&(probability, _, _)| probability)) {
+                    return Err(BuildError::InvalidDistribution(format!("states[{}]", entry)));
+                }
+                for &(_, x, y) in targets.iter() {
+                    match matrix.read_state(x, y) {
+                        Some(target) if target.state.is_occupiable() => {},
+                        Some(_) => return Err(BuildError::TeleportToInvalidField { entry, x, y }),
+                        None => return Err(BuildError::TeleportOutOfRange { entry, x, y })
+                    }
+                }
+            }
+        }
+
+        let start = matrix.cells()
+            .find(|&(_, field)| matches!(field.state, State::StartState(_)))
+            .map(|(position, _)| position);
+        if let (None, Some(entry)) = (start, self.states.iter().position(|(state, _, _)| matches!(*state, State::CliffState(_)))) {
+            return Err(BuildError::CliffWithoutStart(entry));
+        }
+
+        for (entry, terrain) in self.terrains.iter().enumerate() {
+            if let Some(previous_entry) = self.terrains[..entry].iter().position(|previous| previous.name == terrain.name) {
+                return Err(BuildError::DuplicateTerrain { entry, previous_entry });
+            }
+            terrain.transitions.validate(&format!("terrains[{}].transitions", entry))?;
+        }
+
+        let mut covered = HashSet::new();
+        for (entry, &(ref name, x, y)) in self.floors.iter().enumerate() {
+            let terrain = match self.terrains.iter().position(|terrain| terrain.name == *name) {
+                Some(terrain) => terrain,
+                None => return Err(BuildError::UnknownTerrain { entry, name: name.clone() })
+            };
+            let field = match matrix.read_state(x, y) {
+                // terrain replaces only cost of move, own rewards of terminal and special fields are kept
+                Some(field) => match field.state {
+                    State::StartState(_) | State::NormalState(_) =>
+                        Field { reward: self.terrains[terrain].reward, terrain: Some(terrain), ..field.clone() },
+                    _ => Field { terrain: Some(terrain), ..field.clone() }
+                },
+                None => return Err(BuildError::FloorOutOfRange { entry, x, y })
+            };
+            if !covered.insert((x, y)) {
+                let previous_entry = self.floors.iter().position(|&(_, previous_x, previous_y)| (previous_x, previous_y) == (x, y)).unwrap();
+                return Err(BuildError::DuplicateFloor { entry, previous_entry, x, y });
+            }
+            matrix.set_state(field, x, y);
+        }
+
+        let mut blocked_edges = HashMap::new();
+        for (entry, wall) in self.walls.iter().enumerate() {
+            for &(x, y) in [wall.from, wall.to].iter() {
+                if x >= self.x || y >= self.y {
+                    return Err(BuildError::WallOutOfRange { entry, x, y });
+                }
+            }
+            if wall.from.0.abs_diff(wall.to.0) + wall.from.1.abs_diff(wall.to.1) != 1 {
+                return Err(BuildError::WallBetweenDistantFields(entry));
+            }
+            let cost_of_bump = wall.cost_of_bump.unwrap_or(self.cost_of_bump);
+            blocked_edges.insert((wall.to, wall.from), cost_of_bump);
+            if !wall.one_way {
+                blocked_edges.insert((wall.from, wall.to), cost_of_bump);
+            }
+        }
+
+        let mut column_winds = vec![None; self.x];
+        let mut row_winds = vec![None; self.y];
+        let mut entries = HashMap::new();
+        for (entry, wind) in self.winds.iter().enumerate() {
+            let (winds, index, distribution) = match *wind {
+                Wind::Column(x, ref distribution) => (&mut column_winds, x, distribution),
+                Wind::Row(y, ref distribution) => (&mut row_winds, y, distribution)
+            };
+            if index >= winds.len() {
+                return Err(BuildError::WindOutOfRange(entry));
+            }
+            if !transition::is_distribution(distribution.iter().map(|&(probability, _)| probability)) {
+                return Err(BuildError::InvalidDistribution(format!("winds[{}]", entry)));
+            }
+            let key = (matches!(*wind, Wind::Column(..)), index);
+            if let Some(&previous_entry) = entries.get(&key) {
+                return Err(BuildError::DuplicateWind { entry, previous_entry });
+            }
+            entries.insert(key, entry);
+            winds[index] = Some(distribution.iter().filter(|&&(probability, _)| probability > 0.0).cloned().collect());
+        }
+
+        let mut rewarded = HashSet::new();
+        for (entry, &(reward, x, y)) in self.rewards.iter().enumerate() {
+            let field = match matrix.read_state(x, y) {
+                Some(field) => Field { reward, ..field.clone() },
+                None => return Err(BuildError::RewardOutOfRange { entry, x, y })
+            };
+            if !rewarded.insert((x, y)) {
+                let previous_entry = self.rewards.iter().position(|&(_, previous_x, previous_y)| (previous_x, previous_y) == (x, y)).unwrap();
+                return Err(BuildError::DuplicateReward { entry, previous_entry, x, y });
+            }
+            matrix.set_state(field, x, y);
         }
-        Markov {
+
+        let mut markov = Markov {
+            utilities: matrix.map(Field::initial_utility),
+            next_utilities: matrix::Matrix::new(0.0, self.x, self.y),
+            policy: matrix::Matrix::new(None, self.x, self.y),
+            table: TransitionTable::new(0, 0),
+            threads: 1,
             world: matrix,
             gama: self.gama,
-            cost_of_move: self.cost_of_move,
-            p1: self.p1,
-            p2: self.p2,
-            p3: self.p3,
-            p4: (1.0_f64 - self.p1 - self.p2 - self.p3).abs().round_to(2)
-        }
+            transition_model,
+            terrains: self.terrains.clone(),
+            actions: self.actions,
+            blocked_edges,
+            cost_of_bump: self.cost_of_bump,
+            boundary: self.boundary,
+            start,
+            column_winds,
+            row_winds
+        };
+        markov.table = markov.compile_transitions();
+
+        Ok(markov)
     }
-    fn set_size(&mut self, x: usize, y: usize) -> &mut MarkovBuilder {
+    /// Number of columns and rows of the world
+    pub fn set_size(&mut self, x: usize, y: usize) -> &mut MarkovBuilder {
         self.x = x;
         self.y = y;
         self
     }
-    fn set_state(&mut self, state: State, x: usize, y: usize) -> &mut MarkovBuilder {
+    /// State of a field, fields without one are `NormalState(0.0)`
+    pub fn set_state(&mut self, state: State, x: usize, y: usize) -> &mut MarkovBuilder {
         self.states.push((state, x, y));
         self
     }
+    /// Immediate reward of a field, overrides `cost_of_move` (and value of `TerminalState`)
+    pub fn set_reward(&mut self, reward: f64, x: usize, y: usize) -> &mut MarkovBuilder {
+        self.rewards.push((reward, x, y));
+        self
+    }
+    /// Discount factor in (0, 1], worlds with gama 1.0 need a TerminalState or an absorbing edge
+    pub fn set_gama(&mut self, gama: f64) -> &mut MarkovBuilder {
+        self.gama = gama;
+        self
+    }
+    /// Reward of fields which do not define their own
+    pub fn set_cost_of_move(&mut self, cost_of_move: f64) -> &mut MarkovBuilder {
+        self.cost_of_move = cost_of_move;
+        self
+    }
+    /// Edges along x and y axis
+    pub fn set_boundary(&mut self, x: Boundary, y: Boundary) -> &mut MarkovBuilder {
+        self.boundary = (x, y);
+        self
+    }
+    /// Wind of a column or a row, pushing the agent after every move there
+    pub fn add_wind(&mut self, wind: Wind) -> &mut MarkovBuilder {
+        self.winds.push(wind);
+        self
+    }
+    /// Added to the reward when the agent bumps back, zero by default
+    pub fn set_cost_of_bump(&mut self, cost_of_bump: f64) -> &mut MarkovBuilder {
+        self.cost_of_bump = cost_of_bump;
+        self
+    }
+    /// Probabilities of moving forward, to the left and to the right, the rest is probability of moving backward
+    pub fn set_probabilities(&mut self, p1: f64, p2: f64, p3: f64) -> &mut MarkovBuilder {
+        self.p1 = p1;
+        self.p2 = p2;
+        self.p3 = p3;
+        self
+    }
+    /// General slip model, takes precedence over `set_probabilities`
+    pub fn set_transitions(&mut self, transition_model: TransitionModel) -> &mut MarkovBuilder {
+        self.transitions = Some(transition_model);
+        self
+    }
+    /// Actions the agent chooses from, four neighbours by default
+    pub fn set_actions(&mut self, actions: ActionSet) -> &mut MarkovBuilder {
+        self.actions = actions;
+        self
+    }
+    /// Thin wall on the side shared by two neighbouring fields
+    pub fn add_wall(&mut self, wall: Wall) -> &mut MarkovBuilder {
+        self.walls.push(wall);
+        self
+    }
+    /// Named slip model fields can be covered with by `set_floor`
+    pub fn add_terrain(&mut self, terrain: Terrain) -> &mut MarkovBuilder {
+        self.terrains.push(terrain);
+        self
+    }
+    /// Covers a field with a terrain added by `add_terrain`
+    pub fn set_floor(&mut self, name: &str, x: usize, y: usize) -> &mut MarkovBuilder {
+        self.floors.push((name.to_string(), x, y));
+        self
+    }
 }
 
-pub fn left_operation(action: &Action) -> Action {
-    match action {
-        &Action::Up => Action::Left,
-        &Action::Left => Action::Down,
-        &Action::Down => Action::Right,
-        &Action::Right => Action::Up
+/// Coordinate after moving by `delta` along an axis of length `size`, Err(None) when the agent
+/// bumps back from the edge and Err(Some(value)) when it falls off an absorbing edge
+fn along_axis(boundary: Boundary, position: usize, delta: isize, size: usize) -> Result<usize, Option<f64>> {
+    let moved = position as isize + delta;
+    if moved >= 0 && (moved as usize) < size {
+        return Ok(moved as usize);
     }
+    match boundary {
+        Boundary::Wall => Err(None),
+        Boundary::Wrap => Ok(moved.rem_euclid(size as isize) as usize),
+        Boundary::Absorbing(value) => Err(Some(value))
+    }
+}
+
+/// Action turned by 90 degrees to the left, `Stay` is kept
+pub fn left_operation(action: &Action) -> Action {
+    rotate_operation(action, 2)
 }
 
+/// Action turned by 90 degrees to the right, `Stay` is kept
 pub fn right_operation(action: &Action) -> Action {
-    match action {
-        &Action::Up => Action::Right,
-        &Action::Right => Action::Down,
-        &Action::Down => Action::Left,
-        &Action::Left => Action::Up
-    }
+    rotate_operation(action, 6)
 }
 
 pub fn reverse_operation(action: &Action) -> Action {
-    match action {
-        &Action::Up => Action::Down,
-        &Action::Left => Action::Right,
-        &Action::Down => Action::Up,
-        &Action::Right => Action::Left
-    }
+    rotate_operation(action, 4)
+}
+
+/// Action turned counterclockwise by `turns` multiples of 45 degrees
+pub fn rotate_operation(action: &Action, turns: usize) -> Action {
+    Action::from_direction(transition::rotate(action.direction(), turns)).unwrap()
 }
 
 impl Markov {
-    fn new() -> Markov {
-        Markov {
-            world: matrix::Matrix::new(Field::new(State::NormalState(0.0)), 4, 3),
-            gama: 1.0,
-            cost_of_move: -0.04,
-            p1: 0.8,
-            p2: 0.1,
-            p3: 0.1,
-            p4: 0.0
+    /// Field reached by moving by (dx, dy), before winds, teleports and cliffs act, see `transitions` for those
+    #[cfg(test)]
+    fn position_after_move(self: &Markov, direction: (isize, isize), x: usize, y: usize) -> (usize, usize) {
+        match self.try_move(direction, x, y) {
+            Ok(Destination::Field(new_x, new_y)) => (new_x, new_y),
+            _ => (x, y)
         }
     }
 
+    /// Destination of moving by (dx, dy) from (x, y), or the cost of bumping back to (x, y)
+    fn try_move(self: &Markov, (dx, dy): (isize, isize), x: usize, y: usize) -> Result<Destination, f64> {
+        let (size_x, size_y) = self.world.size();
+
+        match (along_axis(self.boundary.0, x, dx, size_x), along_axis(self.boundary.1, y, dy, size_y)) {
+            (Err(Some(value)), _) | (_, Err(Some(value))) => Ok(Destination::OffEdge(value)), // falling off the world
+            (Err(None), _) | (_, Err(None)) => Err(self.cost_of_bump), // going outside world, stay in current place
+            (Ok(new_x), Ok(new_y)) => {
+                if let Some(cost_of_bump) = self.wall_between((x, y), (new_x, new_y)) {
+                    return Err(cost_of_bump); // thin wall bump
+                }
+                match self.world[(new_x, new_y)].state {
+                    State::ProhibitedState => Err(self.cost_of_bump), // stay in my current place (wall bump)
+                    _ => Ok(Destination::Field(new_x, new_y)) // all other places are valid, so just return them
+                }
+            }
+        }
+    }
+
+    /// Cost of bumping into thin walls stopping the move from `from` to the neighbouring `to`, None when
+    /// the move is free. Diagonal move is stopped only when walls or prohibited fields cut both ways around
+    /// the corner, a single wall segment or prohibited field can be passed around, the more expensive way is charged.
+    fn wall_between(self: &Markov, from: (usize, usize), to: (usize, usize)) -> Option<f64> {
+        if from == to {
+            return None;
+        }
+        let edge = |from, to| self.blocked_edges.get(&(from, to)).cloned();
+        if from.0 == to.0 || from.1 == to.1 {
+            return edge(from, to);
+        }
+
+        let around = |corner: (usize, usize)| match self.world[corner].state {
+            State::ProhibitedState => Some(self.cost_of_bump),
+            _ => edge(from, corner).or_else(|| edge(corner, to))
+        };
+        match (around((to.0, from.1)), around((from.0, to.1))) {
+            (Some(first), Some(second)) => Some(first.min(second)),
+            _ => None
+        }
+    }
+
+    #[cfg(test)]
     fn state_after_action(self: &Markov, action: &Action, x: usize, y: usize) -> &State {
-        let (maybe_x, maybe_y) = match action {
-            &Action::Left => (x.checked_sub(1), Some(y)),
-            &Action::Right => (x.checked_add(1), Some(y)),
-            &Action::Up => (Some(x), y.checked_sub(1)),
-            &Action::Down => (Some(x), y.checked_add(1))
+        let (new_x, new_y) = self.position_after_move(action.direction(), x, y);
+        &self.world[(new_x, new_y)].state
+    }
+
+    /// Possible outcomes of an action as (probability, destination, extra reward), in order of the transition model
+    /// of the terrain of (x, y), or of the world when the field has no terrain. Extra reward is the cost of bump
+    /// or the reward of the cliff the agent fell from, zero for other outcomes. Teleport or cliff entered by the move
+    /// acts right away, before the wind could push the agent past it, otherwise the wind pushes the agent and
+    /// a teleport or cliff where the push ends acts.
+    fn transitions(self: &Markov, action: &Action, x: usize, y: usize) -> Vec<(f64, Destination, f64)> {
+        let transition_model = match self.world[(x, y)].terrain {
+            Some(terrain) => &self.terrains[terrain].transitions,
+            None => &self.transition_model
         };
+        let winds = self.winds(x, y);
+        let mut transitions = Vec::new();
+        for &(outcome, probability) in transition_model.distribution(action).iter() {
+            let ((moved_x, moved_y), extra_reward) = match self.try_move(outcome.displacement(action.direction()), x, y) {
+                Ok(Destination::Field(new_x, new_y)) => ((new_x, new_y), 0.0),
+                Ok(destination) => {
+                    transitions.push((probability, destination, 0.0));
+                    continue;
+                },
+                Err(cost_of_bump) => ((x, y), cost_of_bump)
+            };
+            if let State::TeleportState(_) | State::CliffState(_) = self.world[(moved_x, moved_y)].state {
+                self.enter(&mut transitions, probability, Destination::Field(moved_x, moved_y), extra_reward);
+                continue;
+            }
 
-        match (maybe_x, maybe_y) {
-            (Some(_), Some(_)) => {
-                let maybe_state_after_move = self.world.read_state(maybe_x.unwrap(), maybe_y.unwrap());
-                match maybe_state_after_move {
-                    Some(state_after_move) => match state_after_move.state {
-                        State::ProhibitedState => &self.world.read_state(x, y).unwrap().state, // return my current place (wall bump)
-                        _ => &state_after_move.state // all other places are valid, so just return them
-                    },
-                    None => &self.world.read_state(x, y).unwrap().state // going outside world (some index too high), return current place
-                }
+            for &(wind_probability, shift) in winds.iter() {
+                self.enter(&mut transitions, probability*wind_probability, self.blow(shift, moved_x, moved_y), extra_reward);
+            }
+        }
+        transitions
+    }
+
+    /// Appends outcome of reaching `destination`, agent entering a teleport continues to its targets right away
+    /// and falling from a cliff it starts again
+    fn enter(self: &Markov, transitions: &mut Vec<(f64, Destination, f64)>, probability: f64, destination: Destination, extra_reward: f64) {
+        match destination {
+            Destination::Field(new_x, new_y) => match self.world[(new_x, new_y)].state {
+                State::TeleportState(ref targets) => transitions.extend(targets.iter()
+                    .filter(|&&(target_probability, _, _)| target_probability > 0.0)
+                    .map(|&(target_probability, target_x, target_y)|
+                        (probability*target_probability, Destination::Field(target_x, target_y), extra_reward))),
+                State::CliffState(_) => transitions.push((probability, Destination::Field(self.start.unwrap().0, self.start.unwrap().1),
+                                                          extra_reward + self.world[(new_x, new_y)].reward)),
+                _ => transitions.push((probability, destination, extra_reward))
             },
-            _ => &self.world.read_state(x, y).unwrap().state // going outside world (index overflow), return current place
+            destination => transitions.push((probability, destination, extra_reward))
         }
     }
 
-    fn evaluate_action(self: &Markov, action: &Action, x: usize, y: usize) -> (f64) {
-        let forward_state  = self.state_after_action(action, x, y);
-        let left_state     = self.state_after_action(&left_operation(action), x, y);
-        let right_state    = self.state_after_action(&right_operation(action), x, y);
-        let backward_state = self.state_after_action(&reverse_operation(action), x, y);
+    /// Distribution of (probability, shift (dx, dy)) of winds blowing in the column and the row of (x, y)
+    fn winds(self: &Markov, x: usize, y: usize) -> Vec<(f64, (isize, isize))> {
+        let calm = vec![(1.0, 0)];
+        let column_wind = self.column_winds[x].as_ref().unwrap_or(&calm);
+        let row_wind = self.row_winds[y].as_ref().unwrap_or(&calm);
+
+        row_wind.iter()
+            .flat_map(|&(row_probability, dx)| column_wind.iter()
+                .map(move |&(column_probability, dy)| (row_probability*column_probability, (dx, dy))))
+            .collect()
+    }
 
-        let state_to_reward = |state: &State| -> f64 {
-            match state {
-                &State::ProhibitedState => panic!("It should not be able to obtain value from prohibited state"),
-                &State::StartState(value) => value,
-                &State::NormalState(value) => value,
-                &State::TerminalState(value) => value,
-                &State::SpecialState(value, _) => value
+    /// Pushes the agent cell by cell, first along x and then along y axis, until walls stop it
+    /// or it falls off an absorbing edge. Only the field where the push ends counts, as in windy grid world
+    /// of Sutton and Barto the agent may be blown over a TerminalState.
+    fn blow(self: &Markov, (dx, dy): (isize, isize), x: usize, y: usize) -> Destination {
+        let mut position = (x, y);
+        let steps = (0..dx.unsigned_abs()).map(|_| (dx.signum(), 0))
+            .chain((0..dy.unsigned_abs()).map(|_| (0, dy.signum())));
+        for step in steps {
+            match self.try_move(step, position.0, position.1) {
+                Ok(Destination::Field(new_x, new_y)) => position = (new_x, new_y),
+                Ok(destination) => return destination,
+                Err(_) => {}
             }
-        };
+        }
+        Destination::Field(position.0, position.1)
+    }
+
+    /// Compiles transitions of every action of every field the agent chooses action in, other fields get none
+    fn compile_transitions(self: &Markov) -> TransitionTable {
+        let (size_x, size_y) = self.world.size();
+        let mut table = TransitionTable::new(size_x*size_y, self.actions().len());
 
-        let calculate_cost_of_move = |state: &State| -> f64 {
-            match state {
-                &State::SpecialState(_, cost_of_move) => cost_of_move,
-                _ => self.cost_of_move
+        for ((x, y), field) in self.world.cells() {
+            for action in self.actions().iter() {
+                match field.state {
+                    State::TerminalState(_) | State::ProhibitedState | State::TeleportState(_) | State::CliffState(_) => table.push(None),
+                    _ => table.push(self.transitions(action, x, y).into_iter().map(|(probability, destination, extra_reward)| match destination {
+                        Destination::Field(new_x, new_y) => (probability, Target::Field(self.world.index_of(new_x, new_y)), extra_reward),
+                        Destination::OffEdge(value) => (probability, Target::OffEdge(value), extra_reward)
+                    }))
+                }
             }
-        };
+        }
+
+        table
+    }
+
+    /// Position of an action in `actions`, which is its index in the transition table
+    fn action_index(self: &Markov, action: &Action) -> Option<usize> {
+        self.actions().iter().position(|candidate| candidate == action)
+    }
+
+    fn evaluate_action(self: &Markov, action: usize, field: usize) -> f64 {
+        let expected_utility = self.table.expected_utility(field, action, self.utilities.as_slice());
+
+        self.gama*expected_utility + self.world.as_slice()[field].reward + self.table.extra_reward(field, action)
+    }
+
+    #[cfg(test)]
+    fn evaluate_action_at(self: &Markov, action: &Action, x: usize, y: usize) -> f64 {
+        self.evaluate_action(self.action_index(action).unwrap(), self.world.index_of(x, y))
+    }
 
-        let forward_reward  = self.p1*state_to_reward(&forward_state);
-        let left_reward     = self.p2*state_to_reward(&left_state);
-        let right_reward    = self.p3*state_to_reward(&right_state);
-        let backward_reward = self.p4*state_to_reward(&backward_state);
+    fn best_action(self: &Markov, field: usize) -> (Action, f64) {
+        // walk from the least preferred action, so equally good earlier actions win
+        let mut candidates = self.actions().iter().enumerate().rev();
+        let (index, mut action) = candidates.next().unwrap();
+        let mut max = self.evaluate_action(index, field);
 
-        let current_state = &self.world.read_state(x,y).unwrap().state;
+        for (index, candidate) in candidates {
+            let reward = self.evaluate_action(index, field);
+            if reward.max(max) == reward {
+                max = reward;
+                action = candidate;
+            }
+        }
 
-        return self.gama*(forward_reward + left_reward + right_reward + backward_reward) + calculate_cost_of_move(current_state);
+        (action.clone(), max)
     }
 
-    fn evaluate_field(self: &Markov, state: &Field, x: usize, y: usize) -> Field {
-        match state {
-            &Field { state: State::TerminalState(_), .. } => { return state.clone(); },
-            &Field { state: State::ProhibitedState, .. } => { return state.clone(); }
+    fn evaluate_field(self: &Markov, field: &Field, action: &mut Option<Action>, index: usize) -> f64 {
+        match field.state {
+            State::TerminalState(_) => { return field.reward; },
+            State::ProhibitedState | State::TeleportState(_) | State::CliffState(_) => { return self.utilities.as_slice()[index]; },
             _ => {}
         }
 
-        let up_reward    = self.evaluate_action(&Action::Up, x, y);
-        let left_reward  = self.evaluate_action(&Action::Left, x, y);
-        let right_reward = self.evaluate_action(&Action::Right, x, y);
-        let down_reward  = self.evaluate_action(&Action::Down, x, y);
+        let (best_action, max) = self.best_action(index);
+        *action = Some(best_action);
 
-        let mut action: Option<Action> = Some(Action::Down);
-        let mut max = down_reward;
+        max
+    }
 
-        if right_reward.max(max) == right_reward {
-            max = right_reward;
-            action = Some(Action::Right);
+    fn evaluate_policy_field(self: &Markov, field: &Field, action: &mut Option<Action>, index: usize) -> f64 {
+        match field.state {
+            State::TerminalState(_) => { return field.reward; },
+            State::ProhibitedState | State::TeleportState(_) | State::CliffState(_) => { return self.utilities.as_slice()[index]; },
+            _ => {}
         }
-        if left_reward.max(max) == left_reward {
-            max = left_reward;
-            action = Some(Action::Left);
+
+        match action.as_ref().and_then(|action| self.action_index(action)) {
+            Some(action) => self.evaluate_action(action, index),
+            None => self.utilities.as_slice()[index]
         }
-        if up_reward.max(max) == up_reward {
-            max = up_reward;
-            action = Some(Action::Up);
+    }
+
+    fn improve_field(self: &Markov, field: &Field, action: &mut Option<Action>, index: usize) -> f64 {
+        match field.state {
+            State::TerminalState(_) | State::ProhibitedState | State::TeleportState(_) | State::CliffState(_) => { return self.utilities.as_slice()[index]; },
+            _ => {}
         }
 
-        let updated_state = match state {
-            &Field { state: State::ProhibitedState, .. } => State::ProhibitedState,
-            &Field { state: State::StartState(_), .. } => State::StartState(max),
-            &Field { state: State::NormalState(_), .. } => State::NormalState(max),
-            &Field { state: State::TerminalState(_), .. } => State::TerminalState(max),
-            &Field { state: State::SpecialState(_, cost_of_move), .. } => State::SpecialState(max, cost_of_move)
+        let (best_action, max) = self.best_action(index);
+
+        // keep the current action on ties, otherwise policy iteration may never notice that it is stable
+        let keep_current_action = match action.as_ref().and_then(|action| self.action_index(action)) {
+            Some(current_action) => self.evaluate_action(current_action, index) >= max,
+            None => false
         };
+        if !keep_current_action {
+            *action = Some(best_action);
+        }
 
-        Field { state: updated_state, action: action }
+        self.utilities.as_slice()[index]
     }
 
-    pub fn evaluate(self: &mut Markov) -> f64 {
-        let mut new_world = self.world.clone();
-        let mut error = 0.0;
+    /// Updates every field from the current utilities, returns sum of absolute changes of utilities.
+    /// New utilities go to the spare buffer which is then swapped with the current one, actions are
+    /// changed in place as updates read only the action of their own field. Every thread updates
+    /// a block of rows, changes are summed per row and then row by row, so the error does not depend
+    /// on the number of threads either.
+    fn sweep<F>(self: &mut Markov, update: F) -> f64
+        where F: Fn(&Markov, &Field, &mut Option<Action>, usize) -> f64 + Sync {
+        let mut next_utilities = mem::replace(&mut self.next_utilities, matrix::Matrix::new(0.0, 0, 0));
+        let mut policy = mem::replace(&mut self.policy, matrix::Matrix::new(None, 0, 0));
+        let (size_x, size_y) = self.world.size();
+        let block = size_y.div_ceil(self.threads)*size_x;
 
-        let calculate_error = |previous_state: &State, new_state: &State| -> f64 {
-            match (previous_state, new_state) {
-                (&State::ProhibitedState, &State::ProhibitedState) => 0.0,
-                (&State::NormalState(previous_value), &State::NormalState(new_value)) => (new_value - previous_value).abs(),
-                (&State::StartState(previous_value), &State::StartState(new_value)) => (new_value - previous_value).abs(),
-                (&State::SpecialState(previous_value, _), &State::SpecialState(new_value, _)) => (new_value - previous_value).abs(),
-                (&State::TerminalState(previous_value), &State::TerminalState(new_value)) => (new_value - previous_value).abs(),
-                _ => panic!("Type of state changed, it should never happen")
-            }
+        let markov = &*self;
+        let update = &update;
+        let row_errors: Vec<f64> = if self.threads == 1 {
+            markov.sweep_rows(update, 0, next_utilities.as_mut_slice(), policy.as_mut_slice())
+        } else {
+            thread::scope(|scope| {
+                let workers: Vec<_> = next_utilities.as_mut_slice().chunks_mut(block)
+                    .zip(policy.as_mut_slice().chunks_mut(block))
+                    .enumerate()
+                    .map(|(number, (next_utilities, policy))|
+                        scope.spawn(move || markov.sweep_rows(update, number*block, next_utilities, policy)))
+                    .collect();
+                workers.into_iter().flat_map(|worker| worker.join().unwrap()).collect()
+            })
         };
 
-        for (y, row) in self.world.matrix().iter().enumerate() {
-            for (x, elem) in row.iter().enumerate() {
-                let new_state = self.evaluate_field(&elem, x, y);
-                error += calculate_error(&elem.state, &new_state.state);
-                new_world.set_state(new_state, x, y);
-            }
-        }
-
-        self.world = new_world;
+        self.policy = policy;
+        self.next_utilities = mem::replace(&mut self.utilities, next_utilities);
 
-        return error;
+        row_errors.iter().sum()
     }
-}
 
-#[test]
-fn left_operation_calculations() {
-    assert_eq!(Action::Left, left_operation(&Action::Up));
-    assert_eq!(Action::Down, left_operation(&Action::Left));
-    assert_eq!(Action::Right, left_operation(&Action::Down));
-    assert_eq!(Action::Up, left_operation(&Action::Right));
-}
+    /// Updates whole rows starting at field `first`, returns sum of absolute changes of utilities of every row
+    fn sweep_rows<F>(self: &Markov, update: &F, first: usize, next_utilities: &mut [f64], policy: &mut [Option<Action>]) -> Vec<f64>
+        where F: Fn(&Markov, &Field, &mut Option<Action>, usize) -> f64 {
+        let (size_x, _) = self.world.size();
+        let fields = &self.world.as_slice()[first..first + next_utilities.len()];
+        let utilities = &self.utilities.as_slice()[first..first + next_utilities.len()];
+
+        fields.chunks(size_x).zip(utilities.chunks(size_x))
+            .zip(next_utilities.chunks_mut(size_x).zip(policy.chunks_mut(size_x)))
+            .enumerate()
+            .map(|(row, ((fields, utilities), (next_utilities, actions)))| {
+                let mut error = 0.0;
+                for (column, (((field, utility), next_utility), action)) in fields.iter().zip(utilities.iter())
+                    .zip(next_utilities.iter_mut()).zip(actions.iter_mut()).enumerate() {
+                    *next_utility = update(self, field, action, first + row*size_x + column);
+                    error += (*next_utility - utility).abs();
+                }
+                error
+            })
+            .collect()
+    }
+
+    /// Number of columns and rows of the world
+    pub fn size(self: &Markov) -> (usize, usize) {
+        self.world.size()
+    }
+
+    /// Splits every sweep of the solvers across `threads` threads (at least one), each updating a block of rows.
+    /// Utilities, policy and errors are the same for any number of threads.
+    pub fn set_threads(self: &mut Markov, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    pub fn threads(self: &Markov) -> usize {
+        self.threads
+    }
+
+    pub fn gama(self: &Markov) -> f64 {
+        self.gama
+    }
+
+    /// Actions the agent chooses from, in order of preference when they are equally good
+    pub fn actions(self: &Markov) -> &'static [Action] {
+        self.actions.actions()
+    }
+
+    pub fn action_set(self: &Markov) -> ActionSet {
+        self.actions
+    }
+
+    /// State of a field, None for fields outside of the world.
+    pub fn state(self: &Markov, x: usize, y: usize) -> Option<&State> {
+        self.world.read_state(x, y).map(|field| &field.state)
+    }
+
+    /// Name of the terrain covering a field, None for plain fields and fields outside of the world.
+    pub fn terrain(self: &Markov, x: usize, y: usize) -> Option<&str> {
+        self.world.read_state(x, y)
+            .and_then(|field| field.terrain)
+            .map(|terrain| self.terrains[terrain].name.as_str())
+    }
+
+    /// Current utility of a field, None for ProhibitedState, TeleportState, CliffState and fields outside of the world.
+    pub fn utility(self: &Markov, x: usize, y: usize) -> Option<f64> {
+        match self.world.read_state(x, y) {
+            Some(field) if field.state.is_occupiable() => Some(self.utilities[(x, y)]),
+            _ => None
+        }
+    }
+
+    /// Immediate reward of a field, None for ProhibitedState, TeleportState and fields outside of the world.
+    pub fn reward(self: &Markov, x: usize, y: usize) -> Option<f64> {
+        match self.world.read_state(x, y) {
+            Some(field) if field.state.has_reward() => Some(field.reward),
+            _ => None
+        }
+    }
+
+    /// Changes immediate reward of a field, utilities are kept so solving again starts from them.
+    /// Returns false for ProhibitedState, TeleportState and fields outside of the world.
+    pub fn set_reward(self: &mut Markov, x: usize, y: usize, reward: f64) -> bool {
+        let field = match self.world.get_mut(x, y) {
+            Some(field) if field.state.has_reward() => field,
+            _ => return false
+        };
+        field.reward = reward;
+        match field.state {
+            // utility of a terminal field follows its reward
+            State::TerminalState(_) => self.utilities[(x, y)] = reward,
+            // reward of a cliff is the extra reward of falling from it
+            State::CliffState(_) => self.table = self.compile_transitions(),
+            _ => {}
+        }
+        true
+    }
+
+    /// Restores initial utilities from the states of the world and forgets the policy, rewards are kept.
+    pub fn reset(self: &mut Markov) {
+        self.utilities = self.world.map(Field::initial_utility);
+        for action in self.policy.as_mut_slice().iter_mut() {
+            *action = None;
+        }
+    }
+
+    /// Action chosen for a field, None for terminal, prohibited and not yet evaluated fields.
+    pub fn action(self: &Markov, x: usize, y: usize) -> Option<Action> {
+        self.policy.read_state(x, y).and_then(|action| action.clone())
+    }
+
+    /// Coordinates of the StartState field, if the world has one.
+    pub fn start(self: &Markov) -> Option<(usize, usize)> {
+        self.start
+    }
+
+    /// Samples a move from (x, y) out of the transition table, the reward is the reward of the left field (with cost
+    /// of bump when the agent bumped back or reward of the cliff it fell from) and `terminal` holds the reward of the
+    /// TerminalState the agent has reached, or the value of the absorbing edge it fell off (then x and y are of the left field).
+    /// Panics when the action is not one of `actions` or the agent does not choose action in (x, y).
+    pub fn step(self: &Markov, action: &Action, x: usize, y: usize, random: &mut Random) -> Step {
+        let action = self.action_index(action).expect("action outside of the action set of the world");
+        let field = self.world.index_of(x, y);
+        let mut threshold = random.next_f64();
+        let (_, mut target, mut extra_reward) = self.table.successors(field, action).next()
+            .expect("agent does not choose action in this field");
+
+        for (probability, outcome, outcome_reward) in self.table.successors(field, action) {
+            if probability <= 0.0 {
+                continue;
+            }
+            target = outcome;
+            extra_reward = outcome_reward;
+            if threshold < probability {
+                break;
+            }
+            threshold -= probability;
+        }
+
+        let reward = self.world[(x, y)].reward + extra_reward;
+        match target {
+            Target::Field(index) => {
+                let (new_x, new_y) = self.world.coordinates(index);
+                let destination_field = &self.world.as_slice()[index];
+                let terminal = match destination_field.state {
+                    State::TerminalState(_) => Some(destination_field.reward),
+                    _ => None
+                };
+                Step { x: new_x, y: new_y, reward, terminal }
+            },
+            Target::OffEdge(value) => Step { x, y, reward, terminal: Some(value) }
+        }
+    }
+
+    /// Overwrites utility and action of a field, fields the agent cannot choose action in and actions
+    /// outside of `actions` leave it untouched.
+    pub fn set_field(self: &mut Markov, x: usize, y: usize, utility: f64, action: Action) {
+        match self.world.read_state(x, y) {
+            Some(&Field { state: State::TerminalState(_), .. }) | None => {},
+            Some(_) if self.action_index(&action).is_none() => {},
+            Some(field) if field.state.is_occupiable() => {
+                self.utilities[(x, y)] = utility;
+                self.policy[(x, y)] = Some(action);
+            },
+            Some(_) => {}
+        }
+    }
+
+    /// Single sweep of value iteration, returns sum of absolute changes of utilities.
+    pub fn evaluate(self: &mut Markov) -> f64 {
+        self.sweep(Markov::evaluate_field)
+    }
+
+    /// Single sweep of value iteration allocating fresh copies of utilities and policy, as sweeps did before
+    /// the buffers were swapped, so the benchmark can compare both ways.
+    /// Value iteration, calls `evaluate` until error drops below `tolerance`, but at most `max_iterations` times.
+    /// Did not converge when the returned error is still above `tolerance`.
+    pub fn value_iteration(self: &mut Markov, tolerance: f64, max_iterations: usize) -> Convergence {
+        self.iterate(tolerance, max_iterations, |_, _| {})
+    }
+
+    /// Value iteration recording utilities of the initial world and after every call to `evaluate`.
+    pub fn value_iteration_traced(self: &mut Markov, tolerance: f64, max_iterations: usize, trace: &mut Trace) -> Convergence {
+        trace.record(self, None);
+        self.iterate(tolerance, max_iterations, |markov, error| trace.record(markov, Some(error)))
+    }
+
+    fn iterate<F>(self: &mut Markov, tolerance: f64, max_iterations: usize, mut observer: F) -> Convergence
+        where F: FnMut(&Markov, f64) {
+        let mut number_of_iterations = 0;
+        let mut error = self.evaluate();
+        observer(self, error);
+        while error > tolerance && number_of_iterations < max_iterations {
+            number_of_iterations += 1;
+            error = self.evaluate();
+            observer(self, error);
+        }
+        Convergence { iterations: number_of_iterations, error, backups: (number_of_iterations + 1)*self.number_of_decisions(), converged: error <= tolerance }
+    }
+
+    /// Number of fields the agent chooses action in, each sweep backs up all of them
+    fn number_of_decisions(self: &Markov) -> usize {
+        self.world.as_slice().iter().filter(|field| field.state.chooses_action()).count()
+    }
+
+    /// Fields whose actions may lead to every field, each listed once
+    fn predecessors(self: &Markov) -> Vec<Vec<usize>> {
+        let number_of_fields = self.world.as_slice().len();
+        let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); number_of_fields];
+
+        for field in 0..number_of_fields {
+            for action in 0..self.actions().len() {
+                for (_, target, _) in self.table.successors(field, action) {
+                    if let Target::Field(target) = target {
+                        if predecessors[target].last() != Some(&field) {
+                            predecessors[target].push(field);
+                        }
+                    }
+                }
+            }
+        }
+
+        predecessors
+    }
+
+    /// Indices of all fields in the order of in-place sweeps
+    fn sweep_order(self: &Markov, order: SweepOrder) -> Vec<usize> {
+        let number_of_fields = self.world.as_slice().len();
+        match order {
+            SweepOrder::RowMajor => (0..number_of_fields).collect(),
+            SweepOrder::Reverse => (0..number_of_fields).rev().collect(),
+            SweepOrder::TerminalsOutward => {
+                let predecessors = self.predecessors();
+                let next_to_edge = |field: usize| (0..self.actions().len())
+                    .any(|action| self.table.successors(field, action).any(|(_, target, _)| matches!(target, Target::OffEdge(_))));
+                let terminals = (0..number_of_fields).filter(|&field| matches!(self.world.as_slice()[field].state, State::TerminalState(_)));
+                let mut queue: VecDeque<usize> = terminals.chain((0..number_of_fields).filter(|&field| next_to_edge(field))).collect();
+
+                let mut visited = vec![false; number_of_fields];
+                for &field in queue.iter() {
+                    visited[field] = true;
+                }
+                let mut order = Vec::with_capacity(number_of_fields);
+                while let Some(field) = queue.pop_front() {
+                    order.push(field);
+                    for &predecessor in predecessors[field].iter() {
+                        if !visited[predecessor] {
+                            visited[predecessor] = true;
+                            queue.push_back(predecessor);
+                        }
+                    }
+                }
+                order.extend((0..number_of_fields).filter(|&field| !visited[field]));
+                order
+            }
+        }
+    }
+
+    /// Backs up fields one by one in `order`, later fields already see utilities updated earlier in the same sweep.
+    /// Returns sum of absolute changes of utilities.
+    fn sweep_in_place(self: &mut Markov, order: &[usize]) -> f64 {
+        let mut error = 0.0;
+        for &field in order.iter() {
+            if !self.world.as_slice()[field].state.chooses_action() {
+                continue;
+            }
+            let (action, utility) = self.best_action(field);
+            let previous_utility = mem::replace(&mut self.utilities.as_mut_slice()[field], utility);
+            self.policy.as_mut_slice()[field] = Some(action);
+            error += (utility - previous_utility).abs();
+        }
+        error
+    }
+
+    /// Single in-place sweep of value iteration (Gauss-Seidel) visiting fields in `order`, returns sum of absolute changes of utilities.
+    pub fn evaluate_in_place(self: &mut Markov, order: SweepOrder) -> f64 {
+        let order = self.sweep_order(order);
+        self.sweep_in_place(&order)
+    }
+
+    /// Gauss-Seidel value iteration, repeats in-place sweeps in `order` until error drops below `tolerance`,
+    /// but at most `max_iterations` times, counted as in `value_iteration`. Sweeps always run in a single thread.
+    pub fn gauss_seidel(self: &mut Markov, tolerance: f64, max_iterations: usize, order: SweepOrder) -> Convergence {
+        let order = self.sweep_order(order);
+        let mut number_of_iterations = 0;
+        let mut error = self.sweep_in_place(&order);
+        while error > tolerance && number_of_iterations < max_iterations {
+            number_of_iterations += 1;
+            error = self.sweep_in_place(&order);
+        }
+        Convergence { iterations: number_of_iterations, error, backups: (number_of_iterations + 1)*self.number_of_decisions(), converged: error <= tolerance }
+    }
+
+    /// Difference between utility of a field and value of its best action
+    fn bellman_error(self: &Markov, field: usize) -> f64 {
+        (self.best_action(field).1 - self.utilities.as_slice()[field]).abs()
+    }
+
+    /// Prioritized sweeping, repeatedly backs up the field with the largest Bellman error (difference between its
+    /// utility and value of its best action) and then recomputes errors of fields whose actions may lead to it.
+    /// Stops when no error exceeds `tolerance` or after `max_backups` backups. Iterations are the backups,
+    /// error is the largest Bellman error left. Finally every field gets the best action for the reached utilities.
+    pub fn prioritized_sweeping(self: &mut Markov, tolerance: f64, max_backups: usize) -> Convergence {
+        let predecessors = self.predecessors();
+        let number_of_fields = self.world.as_slice().len();
+        let decisions: Vec<usize> = (0..number_of_fields).filter(|&field| self.world.as_slice()[field].state.chooses_action()).collect();
+
+        // errors of queued fields are kept up to date, queue entries with another error are stale
+        let mut errors = vec![0.0; number_of_fields];
+        let mut queue = BinaryHeap::new();
+        for &field in decisions.iter() {
+            errors[field] = self.bellman_error(field);
+            if errors[field] > tolerance {
+                queue.push(Priority(errors[field], field));
+            }
+        }
+
+        let mut backups = 0;
+        while backups < max_backups {
+            let field = match queue.pop() {
+                Some(Priority(error, field)) if error == errors[field] => field,
+                Some(_) => continue,
+                None => break
+            };
+
+            let (action, utility) = self.best_action(field);
+            self.utilities.as_mut_slice()[field] = utility;
+            self.policy.as_mut_slice()[field] = Some(action);
+            errors[field] = 0.0;
+            backups += 1;
+
+            // only fields the agent chooses action in have transitions, so all predecessors are backed up
+            for &predecessor in predecessors[field].iter() {
+                errors[predecessor] = self.bellman_error(predecessor);
+                if errors[predecessor] > tolerance {
+                    queue.push(Priority(errors[predecessor], predecessor));
+                }
+            }
+        }
+
+        for &field in decisions.iter() {
+            self.policy.as_mut_slice()[field] = Some(self.best_action(field).0);
+        }
+
+        let error = errors.iter().fold(0.0, |max: f64, &error| max.max(error));
+        Convergence { iterations: backups, error, backups, converged: error <= tolerance }
+    }
+
+    /// Single sweep of policy evaluation, utilities are updated using the action already stored in every field.
+    pub fn evaluate_policy(self: &mut Markov) -> f64 {
+        self.sweep(Markov::evaluate_policy_field)
+    }
+
+    /// Exact policy evaluation, solves the linear Bellman equations of the action stored in every field.
+    /// Returns the same error measure as `evaluate`.
+    pub fn evaluate_policy_exactly(self: &mut Markov) -> Result<f64, PolicyError> {
+        // every field the agent can be in is an unknown of the system
+        let mut number_of_unknowns = 0;
+        let indices = self.world.map(|field| if field.state.is_occupiable() {
+            number_of_unknowns += 1;
+            Some(number_of_unknowns - 1)
+        } else {
+            None
+        });
+
+        // actions of the unknowns and widths of the band around the diagonal holding coefficients of the system,
+        // fields are numbered row by row, so moves to neighbours stay within about one row of the diagonal
+        let mut actions = vec![None; number_of_unknowns];
+        let (mut lower, mut upper) = (0, 0);
+        for ((x, y), elem) in self.world.cells() {
+            let index = match (indices[(x, y)], &elem.state) {
+                (None, _) | (_, &State::TerminalState(_)) => continue,
+                (Some(index), _) => index
+            };
+            let action = self.policy[(x, y)].as_ref().and_then(|action| self.action_index(action))
+                .ok_or(PolicyError::MissingAction(x, y))?;
+            for (_, target, _) in self.table.successors(self.world.index_of(x, y), action) {
+                if let Target::Field(target) = target {
+                    let column = indices.as_slice()[target].unwrap();
+                    lower = lower.max(index.saturating_sub(column));
+                    upper = upper.max(column.saturating_sub(index));
+                }
+            }
+            actions[index] = Some(action);
+        }
+
+        let entries = linear::BandMatrix::entries(number_of_unknowns, lower, upper);
+        if entries > linear::MAX_ENTRIES {
+            return Err(PolicyError::SystemTooLarge(entries));
+        }
+        let mut a = linear::BandMatrix::new(number_of_unknowns, lower, upper);
+        let mut b = vec![0.0; number_of_unknowns];
+
+        for ((x, y), elem) in self.world.cells() {
+            let index = match indices[(x, y)] {
+                Some(index) => index,
+                None => continue
+            };
+
+            a.add(index, index, 1.0);
+            b[index] = elem.reward;
+            if let Some(action) = actions[index] {
+                let field = self.world.index_of(x, y);
+                for (probability, target, _) in self.table.successors(field, action) {
+                    match target {
+                        Target::Field(target) => a.add(index, indices.as_slice()[target].unwrap(), -self.gama*probability),
+                        Target::OffEdge(value) => b[index] += self.gama*probability*value
+                    }
+                }
+                b[index] += self.table.extra_reward(field, action);
+            }
+        }
+
+        let utilities = linear::solve(a, b).ok_or(PolicyError::SingularSystem)?;
+
+        let mut error = 0.0;
+        for (utility, index) in self.utilities.as_mut_slice().iter_mut().zip(indices.as_slice().iter()) {
+            if let Some(index) = *index {
+                error += (utilities[index] - *utility).abs();
+                *utility = utilities[index];
+            }
+        }
+
+        Ok(error)
+    }
+
+    /// Greedy policy improvement, returns true when no action has changed.
+    pub fn improve_policy(self: &mut Markov) -> bool {
+        let previous_policy = self.policy.clone();
+        self.sweep(Markov::improve_field);
+
+        previous_policy == self.policy
+    }
+
+    /// Policy iteration, alternates policy evaluation (until error drops below `tolerance`, but at most
+    /// `max_evaluation_sweeps` sweeps) with greedy improvement until the policy is stable after an evaluation
+    /// which reached `tolerance`, but at most `max_iterations` improvement steps.
+    /// Iterations are counted in improvement steps, error is the one of the last evaluation sweep
+    /// and backups are those of evaluation sweeps.
+    pub fn policy_iteration(self: &mut Markov, tolerance: f64, max_iterations: usize, max_evaluation_sweeps: usize) -> Convergence {
+        let mut number_of_iterations = 0;
+        let mut total_sweeps = 0;
+        let mut error = 0.0;
+        let mut converged = false;
+
+        // initial policy is greedy with respect to initial utilities
+        self.improve_policy();
+
+        while !converged && number_of_iterations < max_iterations {
+            number_of_iterations += 1;
+
+            let mut number_of_sweeps = 1;
+            error = self.evaluate_policy();
+            while number_of_sweeps < max_evaluation_sweeps && error > tolerance {
+                number_of_sweeps += 1;
+                error = self.evaluate_policy();
+            }
+            total_sweeps += number_of_sweeps;
+
+            // policy unchanged after a truncated evaluation may still be improved by evaluating it further
+            converged = self.improve_policy() && error <= tolerance;
+        }
+
+        Convergence { iterations: number_of_iterations, error, backups: total_sweeps*self.number_of_decisions(), converged }
+    }
+}
+
+#[test]
+fn left_operation_calculations() {
+    assert_eq!(Action::Left, left_operation(&Action::Up));
+    assert_eq!(Action::Down, left_operation(&Action::Left));
+    assert_eq!(Action::Right, left_operation(&Action::Down));
+    assert_eq!(Action::Up, left_operation(&Action::Right));
+}
 
 #[test]
 fn right_operation_calculations() {
@@ -335,7 +1664,7 @@ fn prepare_standard_world() {
         .set_state(State::ProhibitedState, 1, 1)
         .set_state(State::TerminalState(1.0), 3, 0)
         .set_state(State::TerminalState(-1.0), 3, 1)
-        .finalize();
+        .finalize().unwrap();
 
     assert_eq!(State::StartState(0.0), markov.world.read_state(0,2).unwrap().state);
     assert_eq!(State::ProhibitedState, markov.world.read_state(1,1).unwrap().state);
@@ -350,7 +1679,7 @@ fn calculate_state_after_action() {
         .set_state(State::ProhibitedState, 1, 1)
         .set_state(State::TerminalState(1.0), 3, 0)
         .set_state(State::TerminalState(-1.0), 3, 1)
-        .finalize();
+        .finalize().unwrap();
 
     assert_eq!(&State::NormalState(0.0), markov.state_after_action(&Action::Up, 0, 0));
     assert_eq!(&State::NormalState(0.0), markov.state_after_action(&Action::Left, 0, 0));
@@ -383,12 +1712,12 @@ fn calculate_evaluation_of_action() {
         .set_state(State::NormalState(5.0), 0, 1)
         .set_state(State::NormalState(-8.0), 2, 1)
         .set_state(State::NormalState(10.0), 1, 0)
-        .finalize();
+        .finalize().unwrap();
 
-    assert_eq!(0.5, markov.evaluate_action(&Action::Down, 1, 1).round_to(3));
-    assert_eq!(7.7, markov.evaluate_action(&Action::Up, 1, 1).round_to(3));
-    assert_eq!(5.1, markov.evaluate_action(&Action::Left, 1, 1).round_to(3));
-    assert_eq!(-5.3, markov.evaluate_action(&Action::Right, 1, 1).round_to(3));
+    assert_eq!(0.5, markov.evaluate_action_at(&Action::Down, 1, 1).round_to(3));
+    assert_eq!(7.7, markov.evaluate_action_at(&Action::Up, 1, 1).round_to(3));
+    assert_eq!(5.1, markov.evaluate_action_at(&Action::Left, 1, 1).round_to(3));
+    assert_eq!(-5.3, markov.evaluate_action_at(&Action::Right, 1, 1).round_to(3));
 }
 
 #[test]
@@ -399,27 +1728,778 @@ fn calculate_standard_world() {
         .set_state(State::ProhibitedState, 1, 1)
         .set_state(State::TerminalState(1.0), 3, 0)
         .set_state(State::TerminalState(-1.0), 3, 1)
-        .finalize();
+        .finalize().unwrap();
 
     // after ~20 iterations should converge to fast zero error
     for _ in 0..30 {
         markov.evaluate();
     }
 
-    assert_eq!(State::NormalState(0.8115582189599785), markov.world.read_state(0,0).unwrap().state);
-    assert_eq!(State::NormalState(0.8678082191773653), markov.world.read_state(1,0).unwrap().state);
-    assert_eq!(State::NormalState(0.9178082191779183), markov.world.read_state(2,0).unwrap().state);
-    assert_eq!(State::TerminalState(1.0),              markov.world.read_state(3,0).unwrap().state);
+    assert_eq!(Some(0.8115582189599785), markov.utility(0,0));
+    assert_eq!(Some(0.8678082191773653), markov.utility(1,0));
+    assert_eq!(Some(0.9178082191779183), markov.utility(2,0));
+    assert_eq!(Some(1.0),                markov.utility(3,0));
+
+    assert_eq!(Some(0.7615582184462935), markov.utility(0,1));
+    assert_eq!(None,                     markov.utility(1,1));
+    assert_eq!(Some(0.6602739726022764), markov.utility(2,1));
+    assert_eq!(Some(-1.0),               markov.utility(3,1));
+
+    assert_eq!(Some(0.7053082070401893), markov.utility(0,2));
+    assert_eq!(Some(0.6553081816744336), markov.utility(1,2));
+    assert_eq!(Some(0.611415441839725),  markov.utility(2,2));
+    assert_eq!(Some(0.3879247270957595), markov.utility(3,2));
+
+    // utilities are kept apart from the definition of the world
+    assert_eq!(State::StartState(0.0),    markov.world[(0, 2)].state);
+    assert_eq!(State::TerminalState(1.0), markov.world[(3, 0)].state);
+    assert_eq!(State::NormalState(0.0),   markov.world[(0, 0)].state);
+}
+
+#[test]
+fn sweeps_swap_utility_buffers() {
+    let mut markov: Markov = MarkovBuilder::new()
+        .set_state(State::StartState(0.0), 0, 2)
+        .set_state(State::ProhibitedState, 1, 1)
+        .set_state(State::TerminalState(1.0), 3, 0)
+        .set_state(State::TerminalState(-1.0), 3, 1)
+        .finalize().unwrap();
+    let world = markov.world.clone();
+    let unsolved = markov.clone();
+
+    let initial_utilities = markov.utilities.clone();
+    markov.evaluate();
+    assert_eq!(initial_utilities, markov.next_utilities);
+    let first_utilities = markov.utilities.clone();
+    markov.evaluate();
+    assert_eq!(first_utilities, markov.next_utilities);
+    assert_eq!(world, markov.world);
+
+    // spare buffer does not take part in comparison
+    markov.reset();
+    assert_eq!(unsolved, markov);
+}
+
+#[test]
+fn threads_give_same_results_as_single_thread() {
+    let builder = MarkovBuilder::new()
+        .set_size(7, 9)
+        .set_state(State::StartState(0.0), 0, 8)
+        .set_state(State::ProhibitedState, 3, 3)
+        .set_state(State::SpecialState(0.0, -0.3), 2, 5)
+        .set_state(State::TerminalState(1.0), 6, 0)
+        .set_state(State::TerminalState(-1.0), 6, 1)
+        .add_wind(Wind::Column(4, vec![(0.6, -1), (0.4, 0)]))
+        .clone();
+    let mut single = builder.finalize().unwrap();
+    let single_convergence = single.value_iteration(0.0000001, 1000);
+    let mut single_policy = builder.finalize().unwrap();
+    let single_policy_convergence = single_policy.policy_iteration(0.0000001, 100, 100);
+
+    for &threads in [2, 3, 9, 16].iter() {
+        let mut parallel = builder.finalize().unwrap();
+        parallel.set_threads(threads);
+        assert_eq!(threads, parallel.threads());
+        assert_eq!(single_convergence, parallel.value_iteration(0.0000001, 1000));
+        assert_eq!(single.utilities, parallel.utilities);
+        assert_eq!(single.policy, parallel.policy);
+
+        parallel.reset();
+        assert_eq!(single_policy_convergence, parallel.policy_iteration(0.0000001, 100, 100));
+        assert_eq!(single_policy, parallel);
+    }
+}
+
+#[test]
+fn in_place_solvers_match_value_iteration_with_fewer_backups() {
+    let builder = MarkovBuilder::new()
+        .set_state(State::StartState(0.0), 0, 2)
+        .set_state(State::ProhibitedState, 1, 1)
+        .set_state(State::TerminalState(1.0), 3, 0)
+        .set_state(State::TerminalState(-1.0), 3, 1)
+        .clone();
+    let mut synchronous = builder.finalize().unwrap();
+    let convergence = synchronous.value_iteration(0.000001, 1000);
+    assert_eq!(9*(convergence.iterations + 1), convergence.backups);
+
+    assert_eq!(vec![3, 7, 2, 6, 11, 1, 10, 0, 9, 4, 8, 5], synchronous.sweep_order(SweepOrder::TerminalsOutward));
+    assert_eq!(vec![11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0], synchronous.sweep_order(SweepOrder::Reverse));
+
+    let mut solved = Vec::new();
+    for &order in [SweepOrder::RowMajor, SweepOrder::Reverse, SweepOrder::TerminalsOutward].iter() {
+        let mut markov = builder.finalize().unwrap();
+        solved.push((markov.gauss_seidel(0.000001, 1000, order), markov));
+    }
+    let mut markov = builder.finalize().unwrap();
+    solved.push((markov.prioritized_sweeping(0.000001, 10000), markov));
+
+    for (in_place, markov) in solved {
+        assert!(in_place.error <= 0.000001);
+        assert!(in_place.backups < convergence.backups);
+        assert_eq!(synchronous.policy, markov.policy);
+        for (expected, utility) in synchronous.utilities.as_slice().iter().zip(markov.utilities.as_slice().iter()) {
+            assert!((expected - utility).abs() < 0.0001);
+        }
+    }
+}
+
+#[test]
+fn in_place_sweep_uses_utilities_updated_earlier_in_the_same_sweep() {
+    let builder = MarkovBuilder::new()
+        .set_state(State::StartState(0.0), 0, 2)
+        .set_state(State::ProhibitedState, 1, 1)
+        .set_state(State::TerminalState(1.0), 3, 0)
+        .set_state(State::TerminalState(-1.0), 3, 1)
+        .clone();
+    let mut synchronous = builder.finalize().unwrap();
+    let mut in_place = builder.finalize().unwrap();
+    let mut gauss_seidel = builder.finalize().unwrap();
+
+    synchronous.evaluate();
+    let error = in_place.evaluate_in_place(SweepOrder::Reverse);
+    let convergence = gauss_seidel.gauss_seidel(0.000001, 0, SweepOrder::Reverse);
+
+    // (2, 0) is updated before (1, 0) in reverse order, so the reward of the TerminalState already reaches (1, 0)
+    assert!((synchronous.utility(1, 0).unwrap() + 0.04).abs() < 1e-12);
+    assert!(in_place.utility(1, 0).unwrap() > 0.5);
+    assert_eq!(Some(Action::Right), in_place.action(1, 0));
+    assert_eq!(gauss_seidel.utilities, in_place.utilities);
+    assert_eq!(gauss_seidel.policy, in_place.policy);
+    assert_eq!(convergence.error, error);
+}
+
+#[test]
+fn prioritized_sweeping_stops_after_max_backups() {
+    let mut markov: Markov = MarkovBuilder::new()
+        .set_state(State::StartState(0.0), 0, 2)
+        .set_state(State::ProhibitedState, 1, 1)
+        .set_state(State::TerminalState(1.0), 3, 0)
+        .set_state(State::TerminalState(-1.0), 3, 1)
+        .finalize().unwrap();
+
+    let convergence = markov.prioritized_sweeping(0.000001, 5);
+
+    assert_eq!((5, 5), (convergence.iterations, convergence.backups));
+    assert!(convergence.error > 0.000001);
+    // fields which were not backed up yet still get an action
+    assert!((0..4).all(|x| (0..3).all(|y| markov.state(x, y).unwrap().chooses_action() == markov.action(x, y).is_some())));
+}
 
-    assert_eq!(State::NormalState(0.7615582184462935), markov.world.read_state(0,1).unwrap().state);
-    assert_eq!(State::ProhibitedState,                 markov.world.read_state(1,1).unwrap().state);
-    assert_eq!(State::NormalState(0.6602739726022764), markov.world.read_state(2,1).unwrap().state);
-    assert_eq!(State::TerminalState(-1.0),             markov.world.read_state(3,1).unwrap().state);
+#[test]
+fn rewards_are_separate_from_utilities() {
+    let mut markov: Markov = MarkovBuilder::new()
+        .set_state(State::StartState(0.5), 0, 2)
+        .set_state(State::ProhibitedState, 1, 1)
+        .set_state(State::TerminalState(1.0), 3, 0)
+        .set_state(State::TerminalState(-1.0), 3, 1)
+        .set_state(State::SpecialState(0.0, -0.5), 2, 2)
+        .set_reward(-0.1, 1, 2)
+        .set_reward(2.0, 3, 0)
+        .finalize()
+        .unwrap();
+
+    assert_eq!(Some(-0.04), markov.reward(0, 2));
+    assert_eq!(Some(-0.1), markov.reward(1, 2));
+    assert_eq!(Some(-0.5), markov.reward(2, 2));
+    assert_eq!(Some(2.0), markov.reward(3, 0));
+    assert_eq!(Some(-1.0), markov.reward(3, 1));
+    assert_eq!(None, markov.reward(1, 1));
+
+    assert_eq!(Some(0.5), markov.utility(0, 2));
+    assert_eq!(Some(2.0), markov.utility(3, 0));
+
+    markov.value_iteration(0.0001, 1000);
+    assert_eq!(Some(-0.04), markov.reward(0, 2));
+    assert!(markov.utility(0, 2).unwrap() > 1.0);
+
+    markov.reset();
+    assert_eq!(Some(0.5), markov.utility(0, 2));
+    assert_eq!(None, markov.action(0, 2));
+}
+
+#[test]
+fn warm_start_after_reward_edit() {
+    let mut markov: Markov = MarkovBuilder::new()
+        .set_state(State::StartState(0.0), 0, 2)
+        .set_state(State::ProhibitedState, 1, 1)
+        .set_state(State::TerminalState(1.0), 3, 0)
+        .set_state(State::TerminalState(-1.0), 3, 1)
+        .finalize()
+        .unwrap();
+    markov.value_iteration(0.0000001, 1000);
+
+    assert!(markov.set_reward(3, 1, -2.0));
+    assert!(!markov.set_reward(1, 1, -2.0));
+    assert_eq!(Some(-2.0), markov.utility(3, 1));
+    markov.value_iteration(0.0000001, 1000);
+
+    let mut cold = markov.clone();
+    cold.reset();
+    cold.value_iteration(0.0000001, 1000);
+
+    assert!((markov.utility(2, 1).unwrap() - cold.utility(2, 1).unwrap()).abs() < 0.00001);
+    assert_eq!(Some(Action::Left), markov.action(2, 1));
+}
+
+#[test]
+fn display_utilities_and_policy() {
+    let mut markov: Markov = MarkovBuilder::new()
+        .set_state(State::StartState(0.0), 0, 2)
+        .set_state(State::ProhibitedState, 1, 1)
+        .set_state(State::TerminalState(1.0), 3, 0)
+        .set_state(State::TerminalState(-1.0), 3, 1)
+        .set_state(State::SpecialState(0.0, -0.5), 2, 2)
+        .finalize()
+        .unwrap();
+
+    assert_eq!(concat!("Utilities:\n",
+                       "  0.00  0.00  0.00  1.00\n",
+                       "  0.00     #  0.00 -1.00\n",
+                       "  0.00  0.00  0.00  0.00\n",
+                       "Policy:\n",
+                       "  .  .  .  T\n",
+                       "  .  #  .  T\n",
+                       " S.  . B.  .\n"),
+               format!("{:.2}", markov));
+
+    markov.value_iteration(0.0001, 1000);
+
+    assert_eq!(concat!("Utilities:\n",
+                       "  0.8  0.9  0.9  1.0\n",
+                       "  0.8    #  0.7 -1.0\n",
+                       "  0.7  0.7  0.1 -0.1\n",
+                       "Policy:\n",
+                       "  >  >  >  T\n",
+                       "  ^  #  ^  T\n",
+                       " S^  < B<  <\n"),
+               format!("{:.1}", markov));
+}
+
+#[test]
+fn value_iteration_stops_after_max_iterations() {
+    let mut markov: Markov = MarkovBuilder::new()
+        .set_state(State::TerminalState(1.0), 3, 0)
+        .set_state(State::ProhibitedState, 2, 0)
+        .set_state(State::ProhibitedState, 3, 1)
+        .finalize()
+        .unwrap();
+
+    // terminal field is unreachable, so utilities decrease forever
+    let convergence = markov.value_iteration(0.0001, 50);
+
+    assert_eq!(50, convergence.iterations);
+    assert!(convergence.error > 0.0001);
+}
+
+#[test]
+fn policy_iteration_matches_value_iteration() {
+    let builder = {
+        let mut builder = MarkovBuilder::new();
+        builder.set_state(State::StartState(0.0), 0, 2)
+            .set_state(State::ProhibitedState, 1, 1)
+            .set_state(State::TerminalState(1.0), 3, 0)
+            .set_state(State::TerminalState(-1.0), 3, 1);
+        builder
+    };
+
+    let mut value_iteration = builder.finalize().unwrap();
+    while value_iteration.evaluate() > 0.0000001 {}
+
+    let mut policy_iteration = builder.finalize().unwrap();
+    policy_iteration.policy_iteration(0.0000001, 100, 10000);
+
+    assert_eq!(value_iteration.policy, policy_iteration.policy);
+    for (value_utility, policy_utility) in value_iteration.utilities.as_slice().iter().zip(policy_iteration.utilities.as_slice().iter()) {
+        assert!((value_utility - policy_utility).abs() < 0.00001);
+    }
+}
+
+#[test]
+fn policy_iteration_stops_when_policy_is_stable() {
+    let mut markov: Markov = MarkovBuilder::new()
+        .set_state(State::StartState(0.0), 0, 2)
+        .set_state(State::ProhibitedState, 1, 1)
+        .set_state(State::TerminalState(1.0), 3, 0)
+        .set_state(State::TerminalState(-1.0), 3, 1)
+        .finalize().unwrap();
+
+    let convergence = markov.policy_iteration(0.0001, 100, 1000);
+
+    assert!(convergence.converged);
+    assert!(markov.improve_policy());
+    assert_eq!(Some(Action::Right), markov.policy[(0, 0)]);
+    assert_eq!(Some(Action::Up), markov.policy[(0, 2)]);
+    assert_eq!(Some(Action::Left), markov.policy[(3, 2)]);
+    assert_eq!(None, markov.policy[(3, 0)]);
+}
+
+#[test]
+fn policy_iteration_stops_after_max_iterations() {
+    let builder = {
+        let mut builder = MarkovBuilder::new();
+        builder.set_state(State::StartState(0.0), 0, 2)
+            .set_state(State::ProhibitedState, 1, 1)
+            .set_state(State::TerminalState(1.0), 3, 0)
+            .set_state(State::TerminalState(-1.0), 3, 1);
+        builder
+    };
+
+    let mut markov = builder.finalize().unwrap();
+    let convergence = markov.policy_iteration(0.0001, 1, 1000);
+    assert_eq!((1, false), (convergence.iterations, convergence.converged));
+
+    // a single evaluation sweep cannot confirm a stable policy, more improvement steps are taken instead
+    let mut markov = builder.finalize().unwrap();
+    let convergence = markov.policy_iteration(0.0001, 1000, 1);
+    assert!(convergence.converged);
+    assert!(convergence.error <= 0.0001);
+    assert_eq!(Some(Action::Left), markov.policy[(1, 2)]);
+}
+
+#[test]
+fn exact_policy_evaluation_matches_value_iteration() {
+    let mut markov: Markov = MarkovBuilder::new()
+        .set_state(State::StartState(0.0), 0, 2)
+        .set_state(State::ProhibitedState, 1, 1)
+        .set_state(State::TerminalState(1.0), 3, 0)
+        .set_state(State::TerminalState(-1.0), 3, 1)
+        .finalize().unwrap();
+
+    while markov.evaluate() > 0.0000001 {}
+    let solved = markov.clone();
+
+    assert!(markov.evaluate_policy_exactly().unwrap() < 0.0001);
+    for y in 0..3 {
+        for x in 0..4 {
+            assert_eq!(solved.action(x, y), markov.action(x, y));
+            assert_eq!(solved.utility(x, y).map(|value| value.round_to(5)), markov.utility(x, y).map(|value| value.round_to(5)));
+        }
+    }
+}
+
+#[test]
+fn exact_policy_evaluation_requires_actions() {
+    let mut markov: Markov = MarkovBuilder::new()
+        .set_state(State::TerminalState(1.0), 3, 0)
+        .finalize().unwrap();
+
+    assert_eq!(Err(PolicyError::MissingAction(0, 0)), markov.evaluate_policy_exactly());
+}
+
+#[test]
+fn exact_policy_evaluation_detects_policy_never_reaching_terminal() {
+    let mut markov: Markov = MarkovBuilder::new()
+        .set_state(State::TerminalState(1.0), 3, 0)
+        .finalize().unwrap();
+
+    for y in 0..3 {
+        for x in 0..4 {
+            markov.set_field(x, y, 0.0, Action::Left);
+        }
+    }
+    let unchanged = markov.clone();
+
+    assert_eq!(Err(PolicyError::SingularSystem), markov.evaluate_policy_exactly());
+    assert_eq!(unchanged, markov);
+}
+
+#[test]
+fn exact_policy_evaluation_rejects_too_large_systems() {
+    // the agent walks right and then down to the TerminalState in the bottom right corner
+    let walk_to_corner = |size: usize| {
+        let mut markov = MarkovBuilder::new().set_size(size, size).set_state(State::TerminalState(1.0), size - 1, size - 1).finalize().unwrap();
+        for y in 0..size {
+            for x in 0..size {
+                markov.set_field(x, y, 0.0, if x < size - 1 { Action::Right } else { Action::Down });
+            }
+        }
+        markov
+    };
+
+    let mut markov = walk_to_corner(300);
+    let unchanged = markov.clone();
+    assert_eq!(Err(PolicyError::SystemTooLarge(300*300*(2*300 + 300 + 1))), markov.evaluate_policy_exactly());
+    assert_eq!(unchanged, markov);
+
+    // band of a 50x50 world holds only 3 rows of every row of the system
+    let mut markov = walk_to_corner(50);
+    assert!(markov.evaluate_policy_exactly().is_ok());
+    assert!(markov.utility(48, 49).unwrap() > markov.utility(0, 0).unwrap());
+
+    // bands of worlds wrapping along y or with a distant teleport span the whole system, which is stored densely
+    let mut markov = MarkovBuilder::new().set_size(10, 10).set_boundary(Boundary::Wall, Boundary::Wrap)
+        .set_state(State::TerminalState(1.0), 9, 9).finalize().unwrap();
+    for y in 0..10 {
+        for x in 0..10 {
+            markov.set_field(x, y, 0.0, if x < 9 { Action::Right } else { Action::Up });
+        }
+    }
+    assert!(markov.evaluate_policy_exactly().is_ok());
+    assert!(markov.utility(9, 0).unwrap() > markov.utility(0, 0).unwrap());
+
+    let mut markov = MarkovBuilder::new().set_size(10, 10).set_state(State::TerminalState(1.0), 9, 9)
+        .set_state(State::TeleportState(vec![(1.0, 8, 9)]), 1, 0).finalize().unwrap();
+    for y in 0..10 {
+        for x in 0..10 {
+            if markov.state(x, y).unwrap().chooses_action() {
+                markov.set_field(x, y, 0.0, if x < 9 { Action::Right } else { Action::Down });
+            }
+        }
+    }
+    assert!(markov.evaluate_policy_exactly().is_ok());
+    assert!(markov.utility(0, 0).unwrap() > markov.utility(2, 0).unwrap());
+}
+
+#[test]
+fn reject_invalid_worlds() {
+    assert_eq!(Err(BuildError::EmptyWorld(0, 3)),
+               MarkovBuilder::new().set_size(0, 3).set_state(State::TerminalState(1.0), 0, 0).finalize());
+    assert_eq!(Err(BuildError::OutOfRange { entry: 1, x: 4, y: 0 }),
+               MarkovBuilder::new().set_state(State::TerminalState(1.0), 3, 0).set_state(State::ProhibitedState, 4, 0).finalize());
+    assert_eq!(Err(BuildError::DuplicateField { entry: 2, previous_entry: 0, x: 3, y: 0 }),
+               MarkovBuilder::new()
+                   .set_state(State::TerminalState(1.0), 3, 0)
+                   .set_state(State::TerminalState(-1.0), 3, 1)
+                   .set_state(State::ProhibitedState, 3, 0)
+                   .finalize());
+    assert_eq!(Err(BuildError::ProbabilityOutOfRange("p2", -0.1)),
+               MarkovBuilder::new().set_probabilities(0.8, -0.1, 0.3).set_state(State::TerminalState(1.0), 3, 0).finalize());
+    assert_eq!(Err(BuildError::ProbabilitiesExceedOne(1.5)),
+               MarkovBuilder::new().set_probabilities(0.5, 0.5, 0.5).set_state(State::TerminalState(1.0), 3, 0).finalize());
+    assert_eq!(Err(BuildError::GamaOutOfRange(0.0)),
+               MarkovBuilder::new().set_gama(0.0).finalize());
+    assert_eq!(Err(BuildError::NoTerminalState),
+               MarkovBuilder::new().set_state(State::StartState(0.0), 0, 2).finalize());
+}
+
+#[test]
+fn custom_transition_model() {
+    use transition::{Outcome, TransitionModel};
+
+    let mut transition_model = TransitionModel::new(vec![(Outcome::Forward, 0.5), (Outcome::Stay, 0.3), (Outcome::ForwardRight, 0.2)]);
+    transition_model.set_override(Action::Down, vec![(Outcome::Forward, 1.0)]);
+
+    let markov = MarkovBuilder::new()
+        .set_state(State::ProhibitedState, 1, 1)
+        .set_state(State::TerminalState(1.0), 3, 0)
+        .set_transitions(transition_model)
+        .finalize().unwrap();
+
+    // moving up from (0, 2): forward to (0, 1), diagonal to the prohibited field bumps back
+    assert_eq!(vec![(0.5, Destination::Field(0, 1), 0.0), (0.3, Destination::Field(0, 2), 0.0), (0.2, Destination::Field(0, 2), 0.0)], markov.transitions(&Action::Up, 0, 2));
+    assert_eq!(vec![(0.5, Destination::Field(2, 0), 0.0), (0.3, Destination::Field(1, 0), 0.0), (0.2, Destination::Field(2, 1), 0.0)], markov.transitions(&Action::Right, 1, 0));
+    assert_eq!(vec![(1.0, Destination::Field(0, 1), 0.0)], markov.transitions(&Action::Down, 0, 0));
+
+    assert_eq!(Err(BuildError::InvalidDistribution("transitions.default".to_string())),
+               MarkovBuilder::new()
+                   .set_state(State::TerminalState(1.0), 3, 0)
+                   .set_transitions(TransitionModel::new(vec![(Outcome::Forward, 0.5)]))
+                   .finalize());
+}
+
+#[test]
+fn terrain_has_own_slip_model_and_reward() {
+    use transition::{Outcome, TransitionModel};
+
+    let ice = TransitionModel::new(vec![(Outcome::Forward, 0.5), (Outcome::Left, 0.25), (Outcome::Right, 0.25)]);
+    let markov = MarkovBuilder::new()
+        .set_state(State::TerminalState(1.0), 3, 0)
+        .add_terrain(Terrain::new("ice", -0.01, ice))
+        .set_floor("ice", 0, 0)
+        .set_floor("ice", 3, 0)
+        .finalize().unwrap();
+
+    assert_eq!(Some("ice"), markov.terrain(0, 0));
+    assert_eq!(None, markov.terrain(1, 0));
+    assert_eq!(Some(-0.01), markov.reward(0, 0));
+    assert_eq!(Some(-0.04), markov.reward(1, 0));
+    assert_eq!(Some(1.0), markov.reward(3, 0));
+    assert_eq!(vec![(0.5, Destination::Field(1, 0), 0.0), (0.25, Destination::Field(0, 0), 0.0), (0.25, Destination::Field(0, 1), 0.0)], markov.transitions(&Action::Right, 0, 0));
+    assert_eq!(vec![(0.8, Destination::Field(2, 0), 0.0), (0.1, Destination::Field(1, 0), 0.0), (0.1, Destination::Field(1, 1), 0.0)], markov.transitions(&Action::Right, 1, 0));
+}
+
+#[test]
+fn reject_invalid_terrains() {
+    use transition::{Outcome, TransitionModel};
+
+    let mud = Terrain::new("mud", -0.1, TransitionModel::new(vec![(Outcome::Forward, 0.5), (Outcome::Stay, 0.5)]));
+    let mut builder = MarkovBuilder::new();
+    builder.set_state(State::TerminalState(1.0), 3, 0).add_terrain(mud.clone());
+
+    assert_eq!(Err(BuildError::UnknownTerrain { entry: 0, name: "ice".to_string() }),
+               builder.clone().set_floor("ice", 0, 0).finalize());
+    assert_eq!(Err(BuildError::FloorOutOfRange { entry: 0, x: 4, y: 0 }),
+               builder.clone().set_floor("mud", 4, 0).finalize());
+    assert_eq!(Err(BuildError::DuplicateFloor { entry: 1, previous_entry: 0, x: 0, y: 0 }),
+               builder.clone().set_floor("mud", 0, 0).set_floor("mud", 0, 0).finalize());
+    assert_eq!(Err(BuildError::DuplicateTerrain { entry: 1, previous_entry: 0 }),
+               builder.clone().add_terrain(mud).finalize());
+    assert_eq!(Err(BuildError::InvalidDistribution("terrains[1].transitions.default".to_string())),
+               builder.clone().add_terrain(Terrain::new("ice", 0.0, TransitionModel::new(Vec::new()))).finalize());
+}
+
+#[test]
+fn rotate_diagonal_actions() {
+    assert_eq!(Action::UpLeft, rotate_operation(&Action::Up, 1));
+    assert_eq!(Action::DownLeft, left_operation(&Action::UpLeft));
+    assert_eq!(Action::UpLeft, right_operation(&Action::DownLeft));
+    assert_eq!(Action::DownRight, reverse_operation(&Action::UpLeft));
+    assert_eq!(Action::Stay, left_operation(&Action::Stay));
+}
+
+#[test]
+fn eight_neighbour_world_slips_by_45_degrees() {
+    let markov = MarkovBuilder::new()
+        .set_state(State::ProhibitedState, 1, 1)
+        .set_state(State::TerminalState(1.0), 3, 0)
+        .set_actions(ActionSet::EightNeighboursAndStay)
+        .finalize().unwrap();
+
+    assert_eq!(9, markov.actions().len());
+    assert_eq!(&State::TerminalState(1.0), markov.state_after_action(&Action::UpRight, 2, 1));
+    assert_eq!(&State::NormalState(0.0), markov.state_after_action(&Action::DownRight, 0, 0));
+    assert_eq!(vec![(0.8, Destination::Field(3, 0), 0.0), (0.1, Destination::Field(2, 0), 0.0), (0.1, Destination::Field(3, 1), 0.0)], markov.transitions(&Action::UpRight, 2, 1));
+    assert_eq!(vec![(0.8, Destination::Field(0, 0), 0.0), (0.1, Destination::Field(0, 0), 0.0), (0.1, Destination::Field(0, 0), 0.0)], markov.transitions(&Action::Stay, 0, 0));
+
+    let mut markov = markov;
+    markov.value_iteration(0.0001, 1000);
+    assert_eq!(Some(Action::UpRight), markov.action(2, 1));
+    assert!(markov.to_string().contains("\u{2197}"));
+}
+
+#[test]
+fn diagonal_move_cannot_squeeze_between_prohibited_fields() {
+    let markov = MarkovBuilder::new()
+        .set_state(State::ProhibitedState, 1, 0)
+        .set_state(State::ProhibitedState, 0, 1)
+        .set_state(State::ProhibitedState, 2, 1)
+        .set_state(State::TerminalState(1.0), 3, 0)
+        .set_actions(ActionSet::EightNeighbours)
+        .set_cost_of_bump(-0.5)
+        .finalize().unwrap();
+
+    // (1, 0) and (0, 1) touch at the corner crossed between (0, 0) and (1, 1)
+    assert_eq!(Err(-0.5), markov.try_move(Action::DownRight.direction(), 0, 0));
+    assert_eq!(Err(-0.5), markov.try_move(Action::UpLeft.direction(), 1, 1));
+    // a single prohibited field at the corner can be passed around
+    assert_eq!(Ok(Destination::Field(2, 2)), markov.try_move(Action::DownRight.direction(), 1, 1));
+}
+
+#[test]
+fn thin_walls_and_one_way_passages() {
+    let markov = MarkovBuilder::new()
+        .set_state(State::TerminalState(1.0), 3, 0)
+        .set_actions(ActionSet::EightNeighbours)
+        .add_wall(Wall::new((0, 0), (1, 0)))
+        .add_wall(Wall::one_way((0, 1), (0, 2)))
+        .add_wall(Wall::new((1, 1), (1, 2)))
+        .add_wall(Wall::new((2, 1), (2, 2)))
+        .finalize().unwrap();
+
+    assert_eq!((0, 0), markov.position_after_move(Action::Right.direction(), 0, 0));
+    assert_eq!((1, 0), markov.position_after_move(Action::Left.direction(), 1, 0));
+    assert_eq!((0, 2), markov.position_after_move(Action::Down.direction(), 0, 1));
+    assert_eq!((0, 2), markov.position_after_move(Action::Up.direction(), 0, 2));
+    // around the end of a single wall segment, but not through a straight wall
+    assert_eq!((1, 1), markov.position_after_move(Action::DownRight.direction(), 0, 0));
+    assert_eq!((1, 2), markov.position_after_move(Action::DownRight.direction(), 0, 1));
+    assert_eq!((1, 1), markov.position_after_move(Action::DownRight.direction(), 1, 1));
+}
+
+#[test]
+fn bumps_cost_extra() {
+    let mut wall = Wall::new((0, 0), (1, 0));
+    wall.set_cost_of_bump(-2.0);
+    let markov = MarkovBuilder::new()
+        .set_state(State::ProhibitedState, 1, 1)
+        .set_state(State::TerminalState(1.0), 3, 0)
+        .set_cost_of_bump(-0.5)
+        .add_wall(wall)
+        .finalize().unwrap();
+
+    assert_eq!(vec![(0.8, Destination::Field(0, 0), -2.0), (0.1, Destination::Field(0, 0), -0.5), (0.1, Destination::Field(0, 1), 0.0)], markov.transitions(&Action::Right, 0, 0));
+    assert_eq!(vec![(0.8, Destination::Field(1, 2), -0.5), (0.1, Destination::Field(0, 2), 0.0), (0.1, Destination::Field(2, 2), 0.0)], markov.transitions(&Action::Up, 1, 2));
+    // initial utilities are zero except the terminal one, so mostly reward and expected cost of bump remain
+    assert!((markov.evaluate_action_at(&Action::Right, 0, 0) - (-0.04 - 1.6 - 0.05)).abs() < 1e-12);
+    assert!((markov.evaluate_action_at(&Action::Down, 2, 0) - (-0.04 + 0.1)).abs() < 1e-12);
+
+    let mut random = Random::new(3);
+    let steps: Vec<Step> = (0..20).map(|_| markov.step(&Action::Up, 1, 2, &mut random)).collect();
+    assert!(steps.iter().any(|step| (step.x, step.y, step.reward) == (1, 2, -0.54)));
+    assert!(steps.iter().all(|step| step.reward == if (step.x, step.y) == (1, 2) { -0.54 } else { -0.04 }));
+}
+
+#[test]
+fn wrapping_and_absorbing_boundaries() {
+    let mut markov = MarkovBuilder::new()
+        .set_state(State::TerminalState(1.0), 3, 0)
+        .set_boundary(Boundary::Wrap, Boundary::Absorbing(-1.0))
+        .finalize().unwrap();
+
+    assert_eq!(vec![(0.8, Destination::Field(3, 2), 0.0), (0.1, Destination::OffEdge(-1.0), 0.0), (0.1, Destination::Field(0, 1), 0.0)],
+               markov.transitions(&Action::Left, 0, 2));
+    assert_eq!((0, 1), markov.position_after_move(Action::DownRight.direction(), 3, 0));
+
+    let step = markov.step(&Action::Up, 1, 0, &mut Random::new(1));
+    assert_eq!(Step { x: 1, y: 0, reward: -0.04, terminal: Some(-1.0) }, step);
+
+    // falling off is always possible, so the world is solvable even with gama 1 and no TerminalState
+    assert!(MarkovBuilder::new().set_boundary(Boundary::Wall, Boundary::Absorbing(0.0)).finalize().is_ok());
+
+    markov.value_iteration(0.0001, 1000);
+    let value_utilities: Vec<f64> = (0..3).map(|y| markov.utility(0, y).unwrap()).collect();
+    assert!(markov.evaluate_policy_exactly().unwrap() < 0.001);
+    for (y, utility) in value_utilities.iter().enumerate() {
+        assert!((utility - markov.utility(0, y).unwrap()).abs() < 0.001);
+    }
+}
+
+#[test]
+fn teleport_moves_agent_to_its_targets() {
+    let mut markov = MarkovBuilder::new()
+        .set_state(State::TerminalState(1.0), 3, 0)
+        .set_state(State::TeleportState(vec![(0.75, 2, 0), (0.25, 0, 2)]), 1, 0)
+        .set_state(State::TeleportState(vec![(1.0, 3, 0)]), 0, 1)
+        .finalize().unwrap();
+
+    assert_eq!(&State::TeleportState(vec![(1.0, 3, 0)]), markov.state_after_action(&Action::Up, 0, 2));
+    assert_eq!(vec![(0.8, Destination::Field(3, 0), 0.0), (0.1, Destination::Field(0, 2), 0.0), (0.1, Destination::Field(1, 2), 0.0)],
+               markov.transitions(&Action::Up, 0, 2));
+    assert_eq!(vec![(0.8*0.75, Destination::Field(2, 0), 0.0), (0.8*0.25, Destination::Field(0, 2), 0.0),
+                    (0.1, Destination::Field(0, 0), 0.0), (0.1, Destination::Field(3, 0), 0.0)],
+               markov.transitions(&Action::Right, 0, 0));
+
+    let mut random = Random::new(5);
+    assert!((0..20).any(|_| markov.step(&Action::Up, 0, 2, &mut random) == Step { x: 3, y: 0, reward: -0.04, terminal: Some(1.0) }));
+
+    markov.value_iteration(0.0001, 1000);
+    assert_eq!(Some(Action::Up), markov.action(0, 2));
+    assert_eq!((None, None), (markov.utility(1, 0), markov.action(1, 0)));
+    assert!(markov.evaluate_policy_exactly().is_ok());
+    assert!(markov.to_string().contains("   @"));
+
+    assert_eq!(Err(BuildError::TeleportToInvalidField { entry: 1, x: 1, y: 1 }),
+               MarkovBuilder::new()
+                   .set_state(State::TerminalState(1.0), 3, 0)
+                   .set_state(State::TeleportState(vec![(1.0, 1, 1)]), 0, 0)
+                   .set_state(State::ProhibitedState, 1, 1)
+                   .finalize().map(|_| ()));
+    assert_eq!(Err(BuildError::TeleportOutOfRange { entry: 1, x: 5, y: 0 }),
+               MarkovBuilder::new().set_state(State::TerminalState(1.0), 3, 0).set_state(State::TeleportState(vec![(1.0, 5, 0)]), 0, 0).finalize().map(|_| ()));
+    assert_eq!(Err(BuildError::InvalidDistribution("states[1]".to_string())),
+               MarkovBuilder::new().set_state(State::TerminalState(1.0), 3, 0).set_state(State::TeleportState(vec![(0.5, 2, 0)]), 0, 0).finalize().map(|_| ()));
+}
+
+#[test]
+fn cliff_sends_agent_back_to_start() {
+    let mut markov = MarkovBuilder::new()
+        .set_size(4, 2)
+        .set_state(State::StartState(0.0), 0, 1)
+        .set_state(State::CliffState(-100.0), 1, 1)
+        .set_state(State::CliffState(-100.0), 2, 1)
+        .set_state(State::TerminalState(0.0), 3, 1)
+        .set_cost_of_move(-1.0)
+        .set_probabilities(1.0, 0.0, 0.0)
+        .finalize().unwrap();
+
+    assert_eq!(&State::CliffState(-100.0), markov.state_after_action(&Action::Right, 0, 1));
+    assert_eq!(vec![(1.0, Destination::Field(0, 1), -100.0)], markov.transitions(&Action::Right, 0, 1));
+    assert_eq!(vec![(1.0, Destination::Field(0, 1), -100.0)], markov.transitions(&Action::Down, 2, 0));
+    assert_eq!(Step { x: 0, y: 1, reward: -101.0, terminal: None }, markov.step(&Action::Right, 0, 1, &mut Random::new(1)));
+
+    markov.value_iteration(0.0001, 1000);
+    assert_eq!((Some(-100.0), None, None), (markov.reward(1, 1), markov.utility(1, 1), markov.action(1, 1)));
+    assert_eq!(Some(Action::Up), markov.action(0, 1));
+    assert_eq!(Some(-5.0), markov.utility(0, 1));
+    assert!(markov.to_string().contains(" S^  C  C  T"));
+    assert!(markov.evaluate_policy_exactly().is_ok());
+    assert_eq!(Some(-5.0), markov.utility(0, 1).map(|utility| utility.round_to(9)));
+
+    // reward of the cliff is part of the compiled transitions, so changing it takes effect right away
+    assert!(markov.set_reward(1, 1, -10.0));
+    assert_eq!(-16.0, markov.evaluate_action_at(&Action::Right, 0, 1).round_to(9));
+
+    assert_eq!(Err(BuildError::CliffWithoutStart(0)),
+               MarkovBuilder::new().set_state(State::CliffState(-1.0), 1, 0).set_state(State::TerminalState(1.0), 3, 0).finalize().map(|_| ()));
+}
+
+#[test]
+fn wind_pushes_agent_after_move() {
+    let mut markov = MarkovBuilder::new()
+        .set_size(4, 4)
+        .set_state(State::TerminalState(1.0), 3, 3)
+        .set_state(State::ProhibitedState, 2, 0)
+        .set_probabilities(1.0, 0.0, 0.0)
+        .add_wind(Wind::Column(1, vec![(1.0, -2)]))
+        .add_wind(Wind::Column(2, vec![(0.5, -1), (0.5, -2)]))
+        .add_wind(Wind::Row(0, vec![(1.0, 1)]))
+        .finalize().unwrap();
+
+    // wind of the left field blows, edges and prohibited fields stop it without any cost
+    assert_eq!(vec![(0.5, Destination::Field(3, 2), 0.0), (0.5, Destination::Field(3, 1), 0.0)], markov.transitions(&Action::Right, 2, 3));
+    assert_eq!(vec![(1.0, Destination::Field(2, 1), 0.0)], markov.transitions(&Action::Right, 1, 3));
+    assert_eq!(vec![(0.5, Destination::Field(2, 2), 0.0), (0.5, Destination::Field(2, 1), 0.0)], markov.transitions(&Action::Down, 2, 2));
+    assert_eq!(vec![(1.0, Destination::Field(1, 0), 0.0)], markov.transitions(&Action::Up, 1, 1));
+    assert_eq!(vec![(1.0, Destination::Field(1, 0), 0.0)], markov.transitions(&Action::Left, 1, 0));
+    assert_eq!(vec![(1.0, Destination::Field(1, 1), 0.0)], markov.transitions(&Action::Down, 0, 0));
+
+    markov.value_iteration(0.0001, 1000);
+    assert!(markov.evaluate_policy_exactly().is_ok());
+
+    // teleport or cliff entered by the move acts before the wind could push the agent past it
+    let markov = MarkovBuilder::new()
+        .set_size(4, 4)
+        .set_state(State::StartState(0.0), 0, 3)
+        .set_state(State::TerminalState(1.0), 3, 0)
+        .set_state(State::TeleportState(vec![(1.0, 3, 3)]), 1, 2)
+        .set_state(State::CliffState(-100.0), 2, 2)
+        .set_probabilities(1.0, 0.0, 0.0)
+        .add_wind(Wind::Column(1, vec![(1.0, -1)]))
+        .add_wind(Wind::Column(2, vec![(1.0, -1)]))
+        .finalize().unwrap();
+    assert_eq!(vec![(1.0, Destination::Field(3, 3), 0.0)], markov.transitions(&Action::Up, 1, 3));
+    assert_eq!(Step { x: 0, y: 3, reward: -100.04, terminal: None }, markov.step(&Action::Up, 2, 3, &mut Random::new(1)));
+    // wind pushing the agent onto them acts where the push ends
+    assert_eq!(vec![(1.0, Destination::Field(0, 3), -100.0)], markov.transitions(&Action::Right, 1, 3));
+
+    assert_eq!(Err(BuildError::WindOutOfRange(0)),
+               MarkovBuilder::new().set_state(State::TerminalState(1.0), 3, 0).add_wind(Wind::Row(3, vec![(1.0, 1)])).finalize().map(|_| ()));
+    assert_eq!(Err(BuildError::DuplicateWind { entry: 1, previous_entry: 0 }),
+               MarkovBuilder::new().set_state(State::TerminalState(1.0), 3, 0)
+                   .add_wind(Wind::Column(1, vec![(1.0, 1)])).add_wind(Wind::Column(1, vec![(1.0, -1)])).finalize().map(|_| ()));
+    assert_eq!(Err(BuildError::InvalidDistribution("winds[0]".to_string())),
+               MarkovBuilder::new().set_state(State::TerminalState(1.0), 3, 0).add_wind(Wind::Row(0, vec![(0.5, 1)])).finalize().map(|_| ()));
+}
+
+#[test]
+fn reject_invalid_walls() {
+    let mut builder = MarkovBuilder::new();
+    builder.set_state(State::TerminalState(1.0), 3, 0);
+
+    assert_eq!(Err(BuildError::WallOutOfRange { entry: 0, x: 4, y: 0 }),
+               builder.clone().add_wall(Wall::new((3, 0), (4, 0))).finalize());
+    assert_eq!(Err(BuildError::WallBetweenDistantFields(1)),
+               builder.clone().add_wall(Wall::new((0, 0), (0, 1))).add_wall(Wall::new((0, 0), (1, 1))).finalize());
+}
+
+#[test]
+fn reject_invalid_rewards() {
+    assert_eq!(Err(BuildError::RewardOutOfRange { entry: 0, x: 0, y: 3 }),
+               MarkovBuilder::new().set_state(State::TerminalState(1.0), 3, 0).set_reward(1.0, 0, 3).finalize());
+    assert_eq!(Err(BuildError::DuplicateReward { entry: 1, previous_entry: 0, x: 0, y: 0 }),
+               MarkovBuilder::new().set_state(State::TerminalState(1.0), 3, 0).set_reward(1.0, 0, 0).set_reward(2.0, 0, 0).finalize());
+}
+
+#[test]
+fn accept_world_without_terminal_state_when_discounted() {
+    assert!(MarkovBuilder::new().set_gama(0.9).finalize().is_ok());
+}
+
+#[test]
+fn error_message_names_json_entry() {
+    let error = MarkovBuilder::new().set_state(State::TerminalState(1.0), 7, 1).finalize().unwrap_err();
 
-    assert_eq!(State::StartState(0.7053082070401893),  markov.world.read_state(0,2).unwrap().state);
-    assert_eq!(State::NormalState(0.6553081816744336), markov.world.read_state(1,2).unwrap().state);
-    assert_eq!(State::NormalState(0.611415441839725),  markov.world.read_state(2,2).unwrap().state);
-    assert_eq!(State::NormalState(0.3879247270957595), markov.world.read_state(3,2).unwrap().state);
+    assert_eq!("states[0] at (7, 1) lies outside of the world", error.to_string());
 }
 
 #[test]
@@ -428,11 +2508,11 @@ fn update_normal_state() {
     let mut markov: Markov = MarkovBuilder::new()
         .set_state(State::NormalState(4.2), 1,1)
         .set_state(State::NormalState(6.6), 1,1)
-        .finalize();
+        .finalize().unwrap();
 
-    assert_eq!(State::NormalState(0.0), markov.world.read_state(1,1).unwrap().state);
-    assert_eq!(State::NormalState(4.2), markov.world.read_state(1,1).unwrap().state);
-    assert_eq!(State::NormalState(6.6), markov.world.read_state(1,1).unwrap().state);
+    assert_eq!(State::NormalState(0.0), markov.world[(1, 1)].state);
+    assert_eq!(State::NormalState(4.2), markov.world[(1, 1)].state);
+    assert_eq!(State::NormalState(6.6), markov.world[(1, 1)].state);
 }
 
 #[test]
@@ -1,25 +1,47 @@
 use std::clone::Clone;
 use std::fmt;
+use std::ops::{Index, IndexMut};
 
+/// Grid of `x` columns and `y` rows stored row by row in a single buffer, indexed by (x, y)
 #[derive(PartialEq, Clone)]
 pub struct Matrix<T> {
     x: usize,
     y: usize,
-    data: Vec<Vec<T>>
+    data: Vec<T>
 }
 
 impl<T> Matrix<T> {
     pub fn new(default_value: T, xsize: usize, ysize: usize) -> Matrix<T>
         where T: Clone {
         Matrix {
-            data: vec![vec![default_value; xsize]; ysize],
+            data: vec![default_value; xsize*ysize],
+            x: xsize,
+            y: ysize
+        }
+    }
+
+    /// Matrix with every cell computed from its coordinates, filled row by row
+    pub fn from_fn<F>(xsize: usize, ysize: usize, mut cell: F) -> Matrix<T>
+        where F: FnMut(usize, usize) -> T {
+        Matrix {
+            data: (0..xsize*ysize).map(|index| cell(index % xsize, index / xsize)).collect(),
             x: xsize,
             y: ysize
         }
     }
 
     fn is_in_range(self: &Matrix<T>, x: usize, y: usize) -> bool {
-        return x < self.x && y < self.y;
+        x < self.x && y < self.y
+    }
+
+    /// Position of (x, y) in the row by row buffer, see `as_slice`
+    pub fn index_of(self: &Matrix<T>, x: usize, y: usize) -> usize {
+        y*self.x + x
+    }
+
+    /// Coordinates of a position in the row by row buffer
+    pub fn coordinates(self: &Matrix<T>, index: usize) -> (usize, usize) {
+        (index % self.x, index / self.x)
     }
 
     pub fn set_state(self: &mut Matrix<T>, new_state: T, x: usize, y: usize) -> bool {
@@ -27,32 +49,129 @@ impl<T> Matrix<T> {
             return false;
         }
 
-        self.data[y][x] = new_state;
-        return true;
+        let index = self.index_of(x, y);
+        self.data[index] = new_state;
+        true
     }
 
     pub fn read_state(self: &Matrix<T>, x: usize, y: usize) -> Option<&T> {
         if self.is_in_range(x, y) {
-            return Some(&self.data[y][x]);
+            Some(&self.data[self.index_of(x, y)])
         }
         else {
-            return None;
+            None
+        }
+    }
+
+    pub fn get_mut(self: &mut Matrix<T>, x: usize, y: usize) -> Option<&mut T> {
+        if self.is_in_range(x, y) {
+            let index = self.index_of(x, y);
+            Some(&mut self.data[index])
         }
+        else {
+            None
+        }
+    }
+
+    pub fn size(self: &Matrix<T>) -> (usize, usize) {
+        (self.x, self.y)
     }
 
-    pub fn matrix(self: &Matrix<T>) -> &Vec<Vec<T>> {
+    /// All cells row by row
+    pub fn as_slice(self: &Matrix<T>) -> &[T] {
         &self.data
     }
+
+    pub fn as_mut_slice(self: &mut Matrix<T>) -> &mut [T] {
+        &mut self.data
+    }
+
+    pub fn row(self: &Matrix<T>, y: usize) -> &[T] {
+        &self.data[y*self.x..(y + 1)*self.x]
+    }
+
+    /// Rows from the top one
+    pub fn rows(self: &Matrix<T>) -> impl Iterator<Item = &[T]> {
+        // chunks of an empty world would never end, so zero width yields no rows
+        self.data.chunks(self.x.max(1))
+    }
+
+    /// Cells of a column from the top one
+    pub fn column(self: &Matrix<T>, x: usize) -> impl Iterator<Item = &T> {
+        self.data.iter().skip(x).step_by(self.x.max(1)).take(if x < self.x { self.y } else { 0 })
+    }
+
+    /// All cells row by row with their coordinates
+    pub fn cells(self: &Matrix<T>) -> impl Iterator<Item = ((usize, usize), &T)> {
+        let width = self.x;
+        self.data.iter().enumerate().map(move |(index, cell)| ((index % width, index / width), cell))
+    }
+
+    pub fn cells_mut(self: &mut Matrix<T>) -> impl Iterator<Item = ((usize, usize), &mut T)> {
+        let width = self.x;
+        self.data.iter_mut().enumerate().map(move |(index, cell)| ((index % width, index / width), cell))
+    }
+
+    pub fn map<U, F>(self: &Matrix<T>, function: F) -> Matrix<U>
+        where F: FnMut(&T) -> U {
+        Matrix {
+            data: self.data.iter().map(function).collect(),
+            x: self.x,
+            y: self.y
+        }
+    }
+
+    /// Combines cells at the same coordinates, both matrices have to be of the same size
+    pub fn zip_with<U, V, F>(self: &Matrix<T>, other: &Matrix<U>, mut function: F) -> Matrix<V>
+        where F: FnMut(&T, &U) -> V {
+        assert_eq!(self.size(), other.size(), "matrices of different sizes");
+        Matrix {
+            data: self.data.iter().zip(other.data.iter()).map(|(first, second)| function(first, second)).collect(),
+            x: self.x,
+            y: self.y
+        }
+    }
+
+    /// Coordinates of up to eight cells around (x, y) which lie inside of the matrix, row by row
+    pub fn neighbours(self: &Matrix<T>, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> {
+        let (size_x, size_y) = (self.x, self.y);
+        (y.saturating_sub(1)..(y + 2).min(size_y))
+            .flat_map(move |neighbour_y| (x.saturating_sub(1)..(x + 2).min(size_x)).map(move |neighbour_x| (neighbour_x, neighbour_y)))
+            .filter(move |&neighbour| neighbour != (x, y))
+    }
+
+    /// Coordinates of up to four cells sharing a side with (x, y) which lie inside of the matrix
+    pub fn orthogonal_neighbours(self: &Matrix<T>, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> {
+        self.neighbours(x, y).filter(move |&(neighbour_x, neighbour_y)| neighbour_x == x || neighbour_y == y)
+    }
+}
+
+impl<T> Index<(usize, usize)> for Matrix<T> {
+    type Output = T;
+
+    /// Panics when (x, y) lies outside of the matrix
+    fn index(&self, (x, y): (usize, usize)) -> &T {
+        assert!(self.is_in_range(x, y), "({}, {}) lies outside of matrix {}x{}", x, y, self.x, self.y);
+        &self.data[self.index_of(x, y)]
+    }
+}
+
+impl<T> IndexMut<(usize, usize)> for Matrix<T> {
+    fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut T {
+        assert!(self.is_in_range(x, y), "({}, {}) lies outside of matrix {}x{}", x, y, self.x, self.y);
+        let index = self.index_of(x, y);
+        &mut self.data[index]
+    }
 }
 
 impl<T> fmt::Debug for Matrix<T>
     where T: fmt::Debug {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "[");
-        for row in self.data.iter() {
-            write!(f, "\n");
+        write!(f, "[")?;
+        for row in self.rows() {
+            writeln!(f)?;
             for elem in row.iter() {
-                write!(f, "[{:?}], ", elem);
+                write!(f, "[{:?}], ", elem)?;
             }
         }
         write!(f, "]")
@@ -89,7 +208,7 @@ fn all_fields_are_initialized_corectly() {
     let world: Matrix<u64> = Matrix::new(666, 4, 3);
     let mut number_of_calls = 0;
 
-    for row in world.matrix().iter() {
+    for row in world.rows() {
         for elem in row.iter() {
             number_of_calls += 1;
             assert_eq!(elem, &666);
@@ -98,3 +217,52 @@ fn all_fields_are_initialized_corectly() {
 
     assert_eq!(4*3, number_of_calls);
 }
+
+#[test]
+fn index_by_coordinates() {
+    let mut world = Matrix::from_fn(4, 3, |x, y| 10*y + x);
+    world[(3, 1)] = 99;
+
+    assert_eq!(2, world[(2, 0)]);
+    assert_eq!(21, world[(1, 2)]);
+    assert_eq!(Some(&99), world.read_state(3, 1));
+    assert_eq!((1, 2), world.coordinates(world.index_of(1, 2)));
+    assert_eq!(&[20, 21, 22, 23], world.row(2));
+}
+
+#[test]
+#[should_panic]
+fn panic_if_indexed_out_of_range() {
+    let world: Matrix<u64> = Matrix::new(666, 4, 3);
+
+    let _ = world[(4, 0)];
+}
+
+#[test]
+fn iterate_rows_columns_and_cells() {
+    let world = Matrix::from_fn(3, 2, |x, y| 10*y + x);
+
+    assert_eq!(vec![&[0, 1, 2][..], &[10, 11, 12][..]], world.rows().collect::<Vec<_>>());
+    assert_eq!(vec![&1, &11], world.column(1).collect::<Vec<_>>());
+    assert_eq!(0, world.column(3).count());
+    assert_eq!(vec![((0, 0), &0), ((1, 0), &1), ((2, 0), &2), ((0, 1), &10), ((1, 1), &11), ((2, 1), &12)],
+               world.cells().collect::<Vec<_>>());
+}
+
+#[test]
+fn map_and_zip_cells() {
+    let world = Matrix::from_fn(3, 2, |x, y| 10*y + x);
+    let doubled = world.map(|value| 2*value);
+
+    assert_eq!(Matrix::from_fn(3, 2, |x, y| 20*y + 2*x), doubled);
+    assert_eq!(Matrix::from_fn(3, 2, |x, y| 30*y + 3*x), world.zip_with(&doubled, |first, second| first + second));
+}
+
+#[test]
+fn neighbours_inside_of_matrix() {
+    let world: Matrix<u64> = Matrix::new(0, 4, 3);
+
+    assert_eq!(vec![(1, 0), (0, 1), (1, 1)], world.neighbours(0, 0).collect::<Vec<_>>());
+    assert_eq!(8, world.neighbours(1, 1).count());
+    assert_eq!(vec![(3, 1), (2, 2)], world.orthogonal_neighbours(3, 2).collect::<Vec<_>>());
+}
@@ -0,0 +1,189 @@
+use markov::Markov;
+use matrix;
+use random::Random;
+
+/// Tabular Q-learning agent, it learns the world only from moves sampled with `Markov::step`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QLearning {
+    learning_rate: f64,
+    gama: Option<f64>,
+    exploration: f64,
+    episodes: usize,
+    max_steps: usize,
+    seed: u64
+}
+
+impl Default for QLearning {
+    fn default() -> QLearning {
+        QLearning::new()
+    }
+}
+
+impl QLearning {
+    /// Learning rate 0.1, exploration 0.1, 10000 episodes of at most 1000 moves
+    pub fn new() -> QLearning {
+        QLearning {
+            learning_rate: 0.1,
+            gama: None,
+            exploration: 0.1,
+            episodes: 10000,
+            max_steps: 1000,
+            seed: 1
+        }
+    }
+    pub fn set_learning_rate(&mut self, learning_rate: f64) -> &mut QLearning {
+        self.learning_rate = learning_rate;
+        self
+    }
+    /// Discount factor, by default the one of the learned world is used
+    pub fn set_gama(&mut self, gama: f64) -> &mut QLearning {
+        self.gama = Some(gama);
+        self
+    }
+    /// Probability of taking a random action instead of the greedy one
+    pub fn set_exploration(&mut self, exploration: f64) -> &mut QLearning {
+        self.exploration = exploration;
+        self
+    }
+    pub fn episodes(&self) -> usize {
+        self.episodes
+    }
+    pub fn set_episodes(&mut self, episodes: usize) -> &mut QLearning {
+        self.episodes = episodes;
+        self
+    }
+    /// Upper bound of moves in a single episode, episode is cut when no TerminalState is reached
+    pub fn set_max_steps(&mut self, max_steps: usize) -> &mut QLearning {
+        self.max_steps = max_steps;
+        self
+    }
+    pub fn set_seed(&mut self, seed: u64) -> &mut QLearning {
+        self.seed = seed;
+        self
+    }
+
+    /// Runs all episodes from the StartState and returns the world with utilities and actions
+    /// taken from the learned Q table. Fields the agent never moved from keep their initial utility
+    /// and no action. Returns None when the world has no StartState.
+    pub fn learn(&self, markov: &Markov) -> Option<Markov> {
+        let (start_x, start_y) = markov.start()?;
+        let (size_x, size_y) = markov.size();
+        let gama = self.gama.unwrap_or_else(|| markov.gama());
+        let actions = markov.actions();
+        let mut random = Random::new(self.seed);
+        let mut q_table = matrix::Matrix::new(vec![0.0_f64; actions.len()], size_x, size_y);
+        let mut updated = matrix::Matrix::new(false, size_x, size_y);
+
+        for _ in 0..self.episodes {
+            let (mut x, mut y) = (start_x, start_y);
+
+            for _ in 0..self.max_steps {
+                let action_index = if random.next_f64() < self.exploration {
+                    random.below(actions.len())
+                } else {
+                    greedy(&q_table[(x, y)]).0
+                };
+
+                let step = markov.step(&actions[action_index], x, y, &mut random);
+                let future_reward = match step.terminal {
+                    Some(value) => value,
+                    None => greedy(&q_table[(step.x, step.y)]).1
+                };
+
+                let value = &mut q_table[(x, y)][action_index];
+                *value += self.learning_rate*(step.reward + gama*future_reward - *value);
+                updated[(x, y)] = true;
+
+                if step.terminal.is_some() {
+                    break;
+                }
+                x = step.x;
+                y = step.y;
+            }
+        }
+
+        let mut learned = markov.clone();
+        learned.reset();
+        for ((x, y), values) in q_table.cells().filter(|&((x, y), _)| updated[(x, y)]) {
+            let (action_index, utility) = greedy(values);
+            learned.set_field(x, y, utility, actions[action_index].clone());
+        }
+
+        Some(learned)
+    }
+}
+
+/// Index of the best action and its value, ties are resolved in order of `Markov::actions`
+fn greedy(values: &[f64]) -> (usize, f64) {
+    let mut best = 0;
+    for (index, value) in values.iter().enumerate() {
+        if *value > values[best] {
+            best = index;
+        }
+    }
+    (best, values[best])
+}
+
+#[test]
+fn greedy_prefers_first_of_equal_actions() {
+    assert_eq!((0, 0.0), greedy(&[0.0, 0.0, 0.0, 0.0]));
+    assert_eq!((2, 3.0), greedy(&[1.0, -2.0, 3.0, 3.0]));
+}
+
+#[test]
+fn learn_standard_world() {
+    use markov::MarkovBuilder;
+    use markov::State;
+
+    let markov: Markov = MarkovBuilder::new()
+        .set_state(State::StartState(0.0), 0, 2)
+        .set_state(State::ProhibitedState, 1, 1)
+        .set_state(State::TerminalState(1.0), 3, 0)
+        .set_state(State::TerminalState(-1.0), 3, 1)
+        .finalize().unwrap();
+
+    let mut solved = markov.clone();
+    while solved.evaluate() > 0.0001 {}
+
+    let learned = QLearning::new()
+        .set_episodes(20000)
+        .set_learning_rate(0.05)
+        .set_exploration(0.2)
+        .set_seed(7)
+        .learn(&markov)
+        .unwrap();
+
+    // fields along the optimal path are visited often enough to agree with value iteration
+    for &(x, y) in [(0, 2), (0, 1), (0, 0), (1, 0), (2, 0)].iter() {
+        assert_eq!(solved.action(x, y), learned.action(x, y));
+        assert!((solved.utility(x, y).unwrap() - learned.utility(x, y).unwrap()).abs() < 0.1);
+    }
+}
+
+#[test]
+fn unvisited_fields_get_no_action() {
+    use markov::MarkovBuilder;
+    use markov::State;
+
+    let markov: Markov = MarkovBuilder::new()
+        .set_state(State::StartState(0.0), 0, 2)
+        .set_state(State::TerminalState(1.0), 3, 0)
+        .finalize().unwrap();
+
+    // a single move leaves only the StartState
+    let learned = QLearning::new().set_episodes(1).set_max_steps(1).learn(&markov).unwrap();
+
+    assert!(learned.action(0, 2).is_some());
+    assert_eq!((None, Some(0.0)), (learned.action(3, 2), learned.utility(3, 2)));
+    assert_eq!(1, (0..3).flat_map(|y| (0..4).map(move |x| (x, y))).filter(|&(x, y)| learned.action(x, y).is_some()).count());
+}
+
+#[test]
+fn learn_requires_start_state() {
+    use markov::MarkovBuilder;
+    use markov::State;
+
+    let markov: Markov = MarkovBuilder::new().set_state(State::TerminalState(1.0), 3, 0).finalize().unwrap();
+
+    assert_eq!(None, QLearning::new().learn(&markov));
+}
@@ -0,0 +1,61 @@
+/// Small seedable pseudo random number generator (xorshift64*), so simulations are reproducible
+/// without pulling external crates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Random {
+    state: u64
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Random {
+        // xorshift gets stuck on zero, so zero seed is replaced by arbitrary odd constant
+        Random {
+            state: if seed == 0 { 0x9E37_79B9_7F4A_7C15 } else { seed }
+        }
+    }
+
+    pub fn next_u64(self: &mut Random) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Uniformly distributed number from [0, 1)
+    pub fn next_f64(self: &mut Random) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniformly distributed number from [0, bound)
+    pub fn below(self: &mut Random, bound: usize) -> usize {
+        (self.next_f64() * bound as f64) as usize
+    }
+}
+
+#[test]
+fn same_seed_gives_same_sequence() {
+    let mut first = Random::new(42);
+    let mut second = Random::new(42);
+
+    for _ in 0..100 {
+        assert_eq!(first.next_u64(), second.next_u64());
+    }
+}
+
+#[test]
+fn different_seeds_give_different_sequences() {
+    let mut first = Random::new(1);
+    let mut second = Random::new(2);
+
+    assert!(first.next_u64() != second.next_u64());
+}
+
+#[test]
+fn numbers_are_in_range() {
+    let mut random = Random::new(0);
+
+    for _ in 0..1000 {
+        let number = random.next_f64();
+        assert!((0.0..1.0).contains(&number));
+        assert!(random.below(7) < 7);
+    }
+}
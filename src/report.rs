@@ -0,0 +1,96 @@
+use markov::{Action, ActionSet, Markov, State};
+use simulation::Simulation;
+
+/// Kind of a field, without its utility
+#[allow(clippy::enum_variant_names)]
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub enum Kind {
+    ProhibitedState,
+    StartState,
+    TerminalState,
+    SpecialState,
+    NormalState,
+    TeleportState,
+    CliffState
+}
+
+/// Solved field as it is written to machine-readable output
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct FieldReport {
+    pub x: usize,
+    pub y: usize,
+    pub kind: Kind,
+    pub terrain: Option<String>,
+    pub reward: Option<f64>,
+    pub utility: Option<f64>,
+    pub action: Option<Action>
+}
+
+/// Solved world as it is written to machine-readable output, fields are listed row by row
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Report {
+    pub x: usize,
+    pub y: usize,
+    pub actions: ActionSet,
+    pub iterations: usize,
+    pub error: Option<f64>,
+    pub backups: Option<usize>,
+    pub fields: Vec<FieldReport>,
+    /// Monte Carlo simulation of the solved policy, when requested
+    pub simulation: Option<Simulation>
+}
+
+impl Report {
+    /// `error` and `backups` are None for solvers without a convergence measure (Q-learning)
+    pub fn new(markov: &Markov, iterations: usize, error: Option<f64>, backups: Option<usize>) -> Report {
+        let (size_x, size_y) = markov.size();
+        let mut fields = Vec::with_capacity(size_x*size_y);
+
+        for y in 0..size_y {
+            for x in 0..size_x {
+                let kind = match *markov.state(x, y).unwrap() {
+                    State::ProhibitedState => Kind::ProhibitedState,
+                    State::StartState(_) => Kind::StartState,
+                    State::TerminalState(_) => Kind::TerminalState,
+                    State::SpecialState(_, _) => Kind::SpecialState,
+                    State::NormalState(_) => Kind::NormalState,
+                    State::TeleportState(_) => Kind::TeleportState,
+                    State::CliffState(_) => Kind::CliffState
+                };
+                fields.push(FieldReport {
+                    x, y, kind,
+                    terrain: markov.terrain(x, y).map(String::from),
+                    reward: markov.reward(x, y),
+                    utility: markov.utility(x, y),
+                    action: markov.action(x, y)
+                });
+            }
+        }
+
+        Report { x: size_x, y: size_y, actions: markov.action_set(), iterations, error, backups, fields, simulation: None }
+    }
+}
+
+#[test]
+fn report_lists_fields_row_by_row() {
+    use markov::MarkovBuilder;
+
+    let mut markov = MarkovBuilder::new()
+        .set_state(State::StartState(0.0), 0, 2)
+        .set_state(State::ProhibitedState, 1, 1)
+        .set_state(State::TerminalState(1.0), 3, 0)
+        .set_state(State::TerminalState(-1.0), 3, 1)
+        .finalize()
+        .unwrap();
+    let convergence = markov.value_iteration(0.0001, 1000);
+
+    let report = Report::new(&markov, convergence.iterations, Some(convergence.error), Some(convergence.backups));
+
+    assert_eq!((4, 3, ActionSet::FourNeighbours), (report.x, report.y, report.actions));
+    assert_eq!(Some(9*(convergence.iterations + 1)), report.backups);
+    assert_eq!((12, None), (report.fields.len(), report.simulation));
+    assert_eq!(FieldReport { x: 1, y: 1, kind: Kind::ProhibitedState, terrain: None, reward: None, utility: None, action: None }, report.fields[5]);
+    assert_eq!(FieldReport { x: 3, y: 0, kind: Kind::TerminalState, terrain: None, reward: Some(1.0), utility: Some(1.0), action: None }, report.fields[3]);
+    assert_eq!((0, 2, Kind::StartState, Some(Action::Up)),
+               (report.fields[8].x, report.fields[8].y, report.fields[8].kind.clone(), report.fields[8].action.clone()));
+}
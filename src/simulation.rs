@@ -0,0 +1,245 @@
+use markov::{Markov, PolicyError, State};
+use random::Random;
+
+/// How a simulated episode ended
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub enum Ending {
+    /// Agent reached the TerminalState at (x, y)
+    Terminal(usize, usize),
+    /// Agent fell off an absorbing edge of the world from (x, y)
+    OffEdge(usize, usize),
+    /// Episode was cut after the maximal number of moves
+    Cut
+}
+
+/// Single simulated episode, `trajectory` lists visited fields from the StartState (a reached
+/// TerminalState included) and `length` is the number of moves
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Episode {
+    pub trajectory: Vec<(usize, usize)>,
+    pub discounted_return: f64,
+    pub length: usize,
+    pub ending: Ending
+}
+
+/// Simulated episodes together with statistics of their discounted returns. `terminations` holds
+/// the share of episodes ending in every TerminalState as (x, y, rate), row by row, `off_edge`
+/// and `cut` the shares of episodes falling off the world and cut after the maximal number of moves.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Simulation {
+    pub episodes: Vec<Episode>,
+    pub mean: f64,
+    pub stddev: f64,
+    /// 95% confidence interval of the mean, normal approximation
+    pub confidence_interval: (f64, f64),
+    pub terminations: Vec<(usize, usize, f64)>,
+    pub off_edge: f64,
+    pub cut: f64
+}
+
+/// Monte Carlo simulation of the policy of a solved world, episodes start in the StartState,
+/// follow `Markov::action` of every field and moves are sampled with `Markov::step`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonteCarlo {
+    episodes: usize,
+    max_steps: usize,
+    seed: u64
+}
+
+impl Default for MonteCarlo {
+    fn default() -> MonteCarlo {
+        MonteCarlo::new()
+    }
+}
+
+impl MonteCarlo {
+    /// 1000 episodes of at most 1000 moves
+    pub fn new() -> MonteCarlo {
+        MonteCarlo {
+            episodes: 1000,
+            max_steps: 1000,
+            seed: 1
+        }
+    }
+    pub fn episodes(&self) -> usize {
+        self.episodes
+    }
+    pub fn set_episodes(&mut self, episodes: usize) -> &mut MonteCarlo {
+        self.episodes = episodes;
+        self
+    }
+    /// Upper bound of moves in a single episode, episode is cut when no TerminalState is reached
+    pub fn set_max_steps(&mut self, max_steps: usize) -> &mut MonteCarlo {
+        self.max_steps = max_steps;
+        self
+    }
+    pub fn set_seed(&mut self, seed: u64) -> &mut MonteCarlo {
+        self.seed = seed;
+        self
+    }
+
+    /// Simulates all episodes, returns are discounted by gama of the world, so their mean estimates
+    /// utility of the StartState. Fails when there are no episodes to simulate, the world has no StartState
+    /// or an episode enters a field without action.
+    pub fn simulate(&self, markov: &Markov) -> Result<Simulation, PolicyError> {
+        if self.episodes == 0 {
+            return Err(PolicyError::NoEpisodes);
+        }
+        let start = markov.start().ok_or(PolicyError::MissingStartState)?;
+        let mut random = Random::new(self.seed);
+        let episodes = (0..self.episodes)
+            .map(|_| self.episode(markov, start, &mut random))
+            .collect::<Result<Vec<Episode>, PolicyError>>()?;
+
+        let count = episodes.len() as f64;
+        let mean = episodes.iter().map(|episode| episode.discounted_return).sum::<f64>()/count;
+        let stddev = if episodes.len() > 1 {
+            (episodes.iter().map(|episode| (episode.discounted_return - mean).powi(2)).sum::<f64>()/(count - 1.0)).sqrt()
+        } else {
+            0.0
+        };
+        let margin = 1.96*stddev/count.sqrt();
+        let rate = |ending: &Ending| episodes.iter().filter(|episode| episode.ending == *ending).count() as f64/count;
+
+        let (size_x, size_y) = markov.size();
+        let terminations = (0..size_y)
+            .flat_map(|y| (0..size_x).map(move |x| (x, y)))
+            .filter(|&(x, y)| matches!(markov.state(x, y), Some(&State::TerminalState(_))))
+            .map(|(x, y)| (x, y, rate(&Ending::Terminal(x, y))))
+            .collect();
+        let off_edge = episodes.iter().filter(|episode| matches!(episode.ending, Ending::OffEdge(_, _))).count() as f64/count;
+        let cut = rate(&Ending::Cut);
+
+        Ok(Simulation { mean, stddev, confidence_interval: (mean - margin, mean + margin), terminations, off_edge, cut, episodes })
+    }
+
+    fn episode(&self, markov: &Markov, (start_x, start_y): (usize, usize), random: &mut Random) -> Result<Episode, PolicyError> {
+        let (mut x, mut y) = (start_x, start_y);
+        let mut trajectory = vec![(x, y)];
+        let mut discounted_return = 0.0;
+        let mut discount = 1.0;
+
+        for length in 1..=self.max_steps {
+            let action = markov.action(x, y).ok_or(PolicyError::MissingAction(x, y))?;
+            let step = markov.step(&action, x, y, random);
+            discounted_return += discount*step.reward;
+            discount *= markov.gama();
+
+            if let Some(value) = step.terminal {
+                discounted_return += discount*value;
+                let ending = match markov.state(step.x, step.y) {
+                    Some(&State::TerminalState(_)) => {
+                        trajectory.push((step.x, step.y));
+                        Ending::Terminal(step.x, step.y)
+                    },
+                    _ => Ending::OffEdge(step.x, step.y)
+                };
+                return Ok(Episode { trajectory, discounted_return, length, ending });
+            }
+
+            x = step.x;
+            y = step.y;
+            trajectory.push((x, y));
+        }
+
+        Ok(Episode { trajectory, discounted_return, length: self.max_steps, ending: Ending::Cut })
+    }
+}
+
+#[cfg(test)]
+fn solved_standard_world() -> Markov {
+    use markov::MarkovBuilder;
+
+    let mut markov = MarkovBuilder::new()
+        .set_state(State::StartState(0.0), 0, 2)
+        .set_state(State::ProhibitedState, 1, 1)
+        .set_state(State::TerminalState(1.0), 3, 0)
+        .set_state(State::TerminalState(-1.0), 3, 1)
+        .finalize().unwrap();
+    markov.value_iteration(0.000001, 1000);
+    markov
+}
+
+#[test]
+fn mean_return_estimates_utility_of_start() {
+    let markov = solved_standard_world();
+
+    let simulation = MonteCarlo::new().set_episodes(4000).set_seed(3).simulate(&markov).unwrap();
+
+    let (low, high) = simulation.confidence_interval;
+    assert!(low < simulation.mean && simulation.mean < high);
+    assert!((simulation.mean - markov.utility(0, 2).unwrap()).abs() < 2.0*(high - low));
+    assert_eq!(4000, simulation.episodes.len());
+    assert_eq!(vec![3, 3], simulation.terminations.iter().map(|&(x, _, _)| x).collect::<Vec<_>>());
+    assert!(simulation.terminations[0].2 > 0.9);
+    assert!((simulation.terminations.iter().map(|&(_, _, rate)| rate).sum::<f64>() + simulation.off_edge + simulation.cut - 1.0).abs() < 1e-9);
+
+    for episode in simulation.episodes.iter() {
+        assert_eq!(Some(&(0, 2)), episode.trajectory.first());
+        assert_eq!(episode.length + 1, episode.trajectory.len());
+        match episode.ending {
+            Ending::Terminal(x, y) => assert_eq!(Some(&(x, y)), episode.trajectory.last()),
+            ref ending => panic!("unexpected ending {:?}", ending)
+        }
+    }
+}
+
+#[test]
+fn same_seed_gives_same_episodes() {
+    let markov = solved_standard_world();
+
+    let first = MonteCarlo::new().set_episodes(50).set_seed(11).simulate(&markov).unwrap();
+    let second = MonteCarlo::new().set_episodes(50).set_seed(11).simulate(&markov).unwrap();
+    let other = MonteCarlo::new().set_episodes(50).set_seed(12).simulate(&markov).unwrap();
+
+    assert_eq!(first, second);
+    assert!(first.episodes != other.episodes);
+}
+
+#[test]
+fn deterministic_world_has_single_return() {
+    use markov::{Action, Boundary, MarkovBuilder};
+
+    // the agent walks right to the TerminalState, falling off the left edge is costly
+    let mut markov = MarkovBuilder::new()
+        .set_size(4, 1)
+        .set_state(State::StartState(0.0), 0, 0)
+        .set_state(State::TerminalState(1.0), 3, 0)
+        .set_probabilities(1.0, 0.0, 0.0)
+        .set_boundary(Boundary::Absorbing(-10.0), Boundary::Wall)
+        .set_gama(0.5)
+        .finalize().unwrap();
+    markov.value_iteration(0.000001, 1000);
+
+    let simulation = MonteCarlo::new().set_episodes(5).simulate(&markov).unwrap();
+    assert_eq!(Ending::Terminal(3, 0), simulation.episodes[0].ending);
+    assert_eq!(vec![(0, 0), (1, 0), (2, 0), (3, 0)], simulation.episodes[0].trajectory);
+    assert_eq!((vec![(3, 0, 1.0)], 0.0), (simulation.terminations.clone(), simulation.stddev));
+    assert!((simulation.mean - (-0.04 - 0.5*0.04 - 0.25*0.04 + 0.125*1.0)).abs() < 1e-12);
+    assert_eq!((simulation.mean, simulation.mean), simulation.confidence_interval);
+
+    let simulation = MonteCarlo::new().set_episodes(5).set_max_steps(2).simulate(&markov).unwrap();
+    assert_eq!((1.0, 2), (simulation.cut, simulation.episodes[0].length));
+    assert_eq!(vec![(0, 0), (1, 0), (2, 0)], simulation.episodes[0].trajectory);
+
+    markov.set_field(0, 0, 0.0, Action::Left);
+    let simulation = MonteCarlo::new().set_episodes(5).simulate(&markov).unwrap();
+    assert_eq!((Ending::OffEdge(0, 0), 1.0), (simulation.episodes[0].ending.clone(), simulation.off_edge));
+    assert_eq!(vec![(0, 0)], simulation.episodes[0].trajectory);
+    assert!((simulation.mean - (-0.04 + 0.5*-10.0)).abs() < 1e-12);
+}
+
+#[test]
+fn simulation_requires_start_and_policy() {
+    use markov::MarkovBuilder;
+
+    let markov = MarkovBuilder::new().set_state(State::TerminalState(1.0), 3, 0).finalize().unwrap();
+    assert_eq!(Err(PolicyError::MissingStartState), MonteCarlo::new().simulate(&markov));
+
+    let markov = MarkovBuilder::new()
+        .set_state(State::StartState(0.0), 0, 2)
+        .set_state(State::TerminalState(1.0), 3, 0)
+        .finalize().unwrap();
+    assert_eq!(Err(PolicyError::MissingAction(0, 2)), MonteCarlo::new().simulate(&markov));
+    assert_eq!(Err(PolicyError::NoEpisodes), MonteCarlo::new().set_episodes(0).simulate(&markov));
+}
@@ -0,0 +1,119 @@
+/// Successor of a field after an action, index of a field in row by row order or value of the edge the agent fell off
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    Field(usize),
+    OffEdge(f64)
+}
+
+/// Transitions of every field and action compiled into flat arrays, so sweeps walk over numbers only.
+/// Targets past the last field index point to the values of edges the agent falls off. Every successor
+/// keeps its extra reward for sampling, sweeps read only the expected one.
+#[derive(Debug, Clone)]
+pub struct TransitionTable {
+    number_of_fields: usize,
+    number_of_actions: usize,
+    /// successors of field f after action a are at offsets[f*number_of_actions + a]..offsets[f*number_of_actions + a + 1]
+    offsets: Vec<usize>,
+    probabilities: Vec<f64>,
+    targets: Vec<usize>,
+    /// extra reward (cost of bump, reward of cliff) of every successor
+    rewards: Vec<f64>,
+    /// expected extra reward (cost of bump, reward of cliff) of every field and action
+    extra_rewards: Vec<f64>,
+    edge_values: Vec<f64>
+}
+
+impl TransitionTable {
+    pub fn new(number_of_fields: usize, number_of_actions: usize) -> TransitionTable {
+        TransitionTable {
+            number_of_fields,
+            number_of_actions,
+            offsets: vec![0],
+            probabilities: Vec::new(),
+            targets: Vec::new(),
+            rewards: Vec::new(),
+            extra_rewards: Vec::new(),
+            edge_values: Vec::new()
+        }
+    }
+
+    /// Appends successors (probability, target, extra reward) of the next field and action, actions of the first
+    /// field come first. Expected extra reward is added up in order of the successors.
+    pub fn push<I>(self: &mut TransitionTable, successors: I)
+        where I: IntoIterator<Item = (f64, Target, f64)> {
+        let mut extra_reward = 0.0;
+        for (probability, target, reward) in successors {
+            let target = match target {
+                Target::Field(index) => index,
+                Target::OffEdge(value) => {
+                    let edge = match self.edge_values.iter().position(|&edge_value| edge_value == value) {
+                        Some(edge) => edge,
+                        None => {
+                            self.edge_values.push(value);
+                            self.edge_values.len() - 1
+                        }
+                    };
+                    self.number_of_fields + edge
+                }
+            };
+            self.probabilities.push(probability);
+            self.targets.push(target);
+            self.rewards.push(reward);
+            extra_reward += probability*reward;
+        }
+        self.offsets.push(self.probabilities.len());
+        self.extra_rewards.push(extra_reward);
+    }
+
+    /// Successors of a field after an action as (probability, target, extra reward), in order they were pushed
+    pub fn successors(self: &TransitionTable, field: usize, action: usize) -> impl Iterator<Item = (f64, Target, f64)> + '_ {
+        let pair = field*self.number_of_actions + action;
+        let range = self.offsets[pair]..self.offsets[pair + 1];
+        self.probabilities[range.clone()].iter().zip(self.targets[range.clone()].iter()).zip(self.rewards[range].iter())
+            .map(move |((&probability, &target), &reward)| match target.checked_sub(self.number_of_fields) {
+                Some(edge) => (probability, Target::OffEdge(self.edge_values[edge]), reward),
+                None => (probability, Target::Field(target), reward)
+            })
+    }
+
+    /// Sum of probability times utility of every successor, added up in order they were pushed
+    pub fn expected_utility(self: &TransitionTable, field: usize, action: usize, utilities: &[f64]) -> f64 {
+        let pair = field*self.number_of_actions + action;
+        let range = self.offsets[pair]..self.offsets[pair + 1];
+        self.probabilities[range.clone()].iter().zip(self.targets[range].iter())
+            .fold(0.0, |sum, (&probability, &target)| {
+                let utility = if target < self.number_of_fields { utilities[target] } else { self.edge_values[target - self.number_of_fields] };
+                sum + probability*utility
+            })
+    }
+
+    pub fn extra_reward(self: &TransitionTable, field: usize, action: usize) -> f64 {
+        self.extra_rewards[field*self.number_of_actions + action]
+    }
+}
+
+#[test]
+fn successors_are_read_back_in_order() {
+    let mut table = TransitionTable::new(2, 2);
+    table.push(vec![(0.8, Target::Field(1), 0.0), (0.2, Target::OffEdge(-1.0), 0.0)]);
+    table.push(vec![(0.5, Target::Field(0), -1.0), (0.5, Target::Field(1), 0.0)]);
+    table.push(vec![]);
+    table.push(vec![(0.5, Target::OffEdge(2.0), 0.0), (0.5, Target::OffEdge(-1.0), 0.0)]);
+
+    assert_eq!(vec![(0.8, Target::Field(1), 0.0), (0.2, Target::OffEdge(-1.0), 0.0)], table.successors(0, 0).collect::<Vec<_>>());
+    assert_eq!(vec![(0.5, Target::Field(0), -1.0), (0.5, Target::Field(1), 0.0)], table.successors(0, 1).collect::<Vec<_>>());
+    assert_eq!(0, table.successors(1, 0).count());
+    assert_eq!(vec![(0.5, Target::OffEdge(2.0), 0.0), (0.5, Target::OffEdge(-1.0), 0.0)], table.successors(1, 1).collect::<Vec<_>>());
+    assert_eq!(-0.5, table.extra_reward(0, 1));
+    assert_eq!(vec![-1.0, 2.0], table.edge_values);
+}
+
+#[test]
+fn expected_utility_of_fields_and_edges() {
+    let mut table = TransitionTable::new(2, 1);
+    table.push(vec![(0.8, Target::Field(1), 0.0), (0.2, Target::OffEdge(2.0), 0.0)]);
+    table.push(vec![]);
+
+    assert_eq!(0.8*0.5 + 0.2*2.0, table.expected_utility(0, 0, &[0.0, 0.5]));
+    assert_eq!(0.0, table.expected_utility(1, 0, &[0.0, 0.5]));
+}
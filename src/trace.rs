@@ -0,0 +1,112 @@
+use markov::Markov;
+use std::io::{self, Write};
+
+/// Utilities of all fields recorded after every iteration of a solver, written as CSV for plotting.
+/// Fields the agent cannot be in (prohibited, teleport and cliff) have no utility, so they have no column either.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trace {
+    fields: Vec<(usize, usize)>,
+    rows: Vec<(Option<f64>, Vec<f64>)>
+}
+
+impl Default for Trace {
+    fn default() -> Trace {
+        Trace::new()
+    }
+}
+
+impl Trace {
+    pub fn new() -> Trace {
+        Trace {
+            fields: Vec::new(),
+            rows: Vec::new()
+        }
+    }
+
+    /// Records current utilities of the world, `error` is None for the initial state of the world.
+    /// Columns are taken from the first recorded world.
+    pub fn record(&mut self, markov: &Markov, error: Option<f64>) {
+        if self.rows.is_empty() {
+            let (size_x, size_y) = markov.size();
+            self.fields = (0..size_y)
+                .flat_map(|y| (0..size_x).map(move |x| (x, y)))
+                .filter(|&(x, y)| markov.utility(x, y).is_some())
+                .collect();
+        }
+
+        let utilities = self.fields.iter().map(|&(x, y)| markov.utility(x, y).unwrap()).collect();
+        self.rows.push((error, utilities));
+    }
+
+    /// Number of recorded iterations, including the initial state
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// One row per iteration, columns are `iteration`, `error` and utility of every field labelled `x<x>y<y>`
+    pub fn write_csv<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write!(writer, "iteration,error")?;
+        for &(x, y) in self.fields.iter() {
+            write!(writer, ",x{}y{}", x, y)?;
+        }
+        writeln!(writer)?;
+
+        for (iteration, &(error, ref utilities)) in self.rows.iter().enumerate() {
+            write!(writer, "{},", iteration)?;
+            if let Some(error) = error {
+                write!(writer, "{}", error)?;
+            }
+            for utility in utilities.iter() {
+                write!(writer, ",{}", utility)?;
+            }
+            writeln!(writer)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn write_trace_of_value_iteration() {
+    use markov::{MarkovBuilder, State};
+
+    let mut markov = MarkovBuilder::new()
+        .set_size(2, 1)
+        .set_state(State::TerminalState(1.0), 1, 0)
+        .set_probabilities(1.0, 0.0, 0.0)
+        .set_cost_of_move(-0.5)
+        .finalize()
+        .unwrap();
+    let mut trace = Trace::new();
+
+    markov.value_iteration_traced(0.0001, 1000, &mut trace);
+
+    let mut output = Vec::new();
+    trace.write_csv(&mut output).unwrap();
+
+    assert_eq!(3, trace.len());
+    assert_eq!("iteration,error,x0y0,x1y0\n0,,0,1\n1,0.5,0.5,1\n2,0,0.5,1\n", String::from_utf8(output).unwrap());
+}
+
+#[test]
+fn skip_prohibited_fields() {
+    use markov::{MarkovBuilder, State};
+
+    let markov = MarkovBuilder::new()
+        .set_size(3, 1)
+        .set_state(State::ProhibitedState, 1, 0)
+        .set_state(State::TerminalState(1.0), 2, 0)
+        .finalize()
+        .unwrap();
+    let mut trace = Trace::new();
+    trace.record(&markov, None);
+
+    let mut output = Vec::new();
+    trace.write_csv(&mut output).unwrap();
+
+    assert_eq!("iteration,error,x0y0,x2y0\n0,,0,1\n", String::from_utf8(output).unwrap());
+}
@@ -0,0 +1,191 @@
+use markov::{Action, BuildError};
+
+/// Movement of the agent relative to the intended direction, diagonal outcomes are turned by 45 degrees
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Forward,
+    Left,
+    Right,
+    Back,
+    Stay,
+    ForwardLeft,
+    ForwardRight,
+    BackLeft,
+    BackRight
+}
+
+/// Probabilities of outcomes of a single intended action
+pub type Distribution = Vec<(Outcome, f64)>;
+
+/// Stochastic slip model, the same distribution is used for every action unless it is overridden
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TransitionModel {
+    default: Distribution,
+    #[serde(default)]
+    overrides: Vec<(Action, Distribution)>
+}
+
+/// Tolerance of comparing sums of probabilities, so values like 0.7 + 0.2 + 0.1 are accepted
+pub const EPSILON: f64 = 1e-9;
+
+/// Neighbours of a field, counterclockwise on the screen (y grows downwards) starting upwards
+const COMPASS: [(isize, isize); 8] = [(0, -1), (-1, -1), (-1, 0), (-1, 1), (0, 1), (1, 1), (1, 0), (1, -1)];
+
+/// Direction to a neighbour turned counterclockwise by `turns` multiples of 45 degrees, no move stays no move
+pub fn rotate(direction: (isize, isize), turns: usize) -> (isize, isize) {
+    match COMPASS.iter().position(|&neighbour| neighbour == direction) {
+        Some(index) => COMPASS[(index + turns) % COMPASS.len()],
+        None => direction
+    }
+}
+
+/// Whether all probabilities lie in [0, 1] and sum up to 1
+pub fn is_distribution<I>(probabilities: I) -> bool
+    where I: IntoIterator<Item = f64> {
+    let mut sum = 0.0;
+    for probability in probabilities {
+        if !(0.0..=1.0).contains(&probability) {
+            return false;
+        }
+        sum += probability;
+    }
+    (sum - 1.0).abs() <= EPSILON
+}
+
+impl Outcome {
+    /// Displacement (dx, dy) of the agent intending to move by `direction`
+    pub fn displacement(&self, direction: (isize, isize)) -> (isize, isize) {
+        let turns = match *self {
+            Outcome::Stay => return (0, 0),
+            Outcome::Forward => 0,
+            Outcome::ForwardLeft => 1,
+            Outcome::Left => 2,
+            Outcome::BackLeft => 3,
+            Outcome::Back => 4,
+            Outcome::BackRight => 5,
+            Outcome::Right => 6,
+            Outcome::ForwardRight => 7
+        };
+        rotate(direction, turns)
+    }
+}
+
+impl TransitionModel {
+    pub fn new(default: Distribution) -> TransitionModel {
+        TransitionModel {
+            default,
+            overrides: Vec::new()
+        }
+    }
+
+    /// Textbook model: `p1` forward, `p2` left, `p3` right and the rest backward
+    pub fn from_probabilities(p1: f64, p2: f64, p3: f64) -> TransitionModel {
+        TransitionModel::from_slips(p1, p2, p3, Outcome::Left, Outcome::Right)
+    }
+
+    /// Textbook model with slips `left` and `right` taken with probabilities `p2` and `p3`
+    pub fn from_slips(p1: f64, p2: f64, p3: f64, left: Outcome, right: Outcome) -> TransitionModel {
+        // rest below rounding errors of the sum is not a real probability of moving backward
+        let p4 = 1.0 - p1 - p2 - p3;
+
+        let mut default = vec![(Outcome::Forward, p1), (left, p2), (right, p3)];
+        if p4 > EPSILON {
+            default.push((Outcome::Back, p4));
+        }
+        TransitionModel::new(default)
+    }
+
+    /// Replaces distribution of a single action
+    pub fn set_override(&mut self, action: Action, distribution: Distribution) -> &mut TransitionModel {
+        self.overrides.retain(|(overridden, _)| *overridden != action);
+        self.overrides.push((action, distribution));
+        self
+    }
+
+    /// Distribution of outcomes of an action, outcomes with zero probability are left out
+    pub fn distribution(&self, action: &Action) -> Distribution {
+        self.overrides.iter()
+            .find(|(overridden, _)| overridden == action)
+            .map_or(&self.default, |(_, distribution)| distribution)
+            .iter()
+            .filter(|&&(_, probability)| probability > 0.0)
+            .cloned()
+            .collect()
+    }
+
+    /// Checks that every distribution consists of probabilities summing up to 1,
+    /// errors refer to the model by its name in JSON input
+    pub fn validate(&self, name: &str) -> Result<(), BuildError> {
+        let is_valid = |distribution: &Distribution| is_distribution(distribution.iter().map(|&(_, probability)| probability));
+
+        if !is_valid(&self.default) {
+            return Err(BuildError::InvalidDistribution(format!("{}.default", name)));
+        }
+        for (entry, (_, distribution)) in self.overrides.iter().enumerate() {
+            if !is_valid(distribution) {
+                return Err(BuildError::InvalidDistribution(format!("{}.overrides[{}]", name, entry)));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn displacement_relative_to_direction() {
+    let up = (0, -1);
+
+    assert_eq!((0, -1), Outcome::Forward.displacement(up));
+    assert_eq!((-1, 0), Outcome::Left.displacement(up));
+    assert_eq!((1, 0), Outcome::Right.displacement(up));
+    assert_eq!((0, 1), Outcome::Back.displacement(up));
+    assert_eq!((0, 0), Outcome::Stay.displacement(up));
+    assert_eq!((-1, -1), Outcome::ForwardLeft.displacement(up));
+    assert_eq!((1, -1), Outcome::ForwardRight.displacement(up));
+    assert_eq!((-1, 1), Outcome::BackLeft.displacement(up));
+    assert_eq!((1, 1), Outcome::BackRight.displacement(up));
+
+    assert_eq!((0, -1), Outcome::Left.displacement((1, 0)));
+    assert_eq!((1, 1), Outcome::ForwardRight.displacement((1, 0)));
+    assert_eq!((0, -1), Outcome::ForwardLeft.displacement((1, -1)));
+    assert_eq!((0, 0), Outcome::Back.displacement((0, 0)));
+}
+
+#[test]
+fn textbook_model_leaves_out_backward_move() {
+    let model = TransitionModel::from_probabilities(0.8, 0.1, 0.1);
+
+    assert_eq!(vec![(Outcome::Forward, 0.8), (Outcome::Left, 0.1), (Outcome::Right, 0.1)], model.distribution(&Action::Up));
+    assert_eq!(Ok(()), model.validate("transitions"));
+
+    let model = TransitionModel::from_probabilities(0.7, 0.1, 0.1);
+    assert_eq!(Outcome::Back, model.distribution(&Action::Up)[3].0);
+}
+
+#[test]
+fn override_distribution_of_single_action() {
+    let mut model = TransitionModel::new(vec![(Outcome::Forward, 0.9), (Outcome::Stay, 0.1), (Outcome::Back, 0.0)]);
+    model.set_override(Action::Down, vec![(Outcome::Forward, 1.0)]);
+
+    assert_eq!(vec![(Outcome::Forward, 0.9), (Outcome::Stay, 0.1)], model.distribution(&Action::Up));
+    assert_eq!(vec![(Outcome::Forward, 1.0)], model.distribution(&Action::Down));
+}
+
+#[test]
+fn reject_distributions_not_summing_to_one() {
+    let mut model = TransitionModel::new(vec![(Outcome::Forward, 0.9)]);
+    assert_eq!(Err(BuildError::InvalidDistribution("transitions.default".to_string())), model.validate("transitions"));
+
+    model = TransitionModel::new(vec![(Outcome::Forward, 1.0)]);
+    model.set_override(Action::Left, vec![(Outcome::Forward, 1.5), (Outcome::Back, -0.5)]);
+    assert_eq!(Err(BuildError::InvalidDistribution("transitions.overrides[0]".to_string())), model.validate("transitions"));
+}
+
+#[test]
+fn distribution_tolerates_rounding_errors_only() {
+    assert!(is_distribution(vec![0.7, 0.2, 0.1]));
+    assert!(is_distribution(vec![1.0, 0.0]));
+    assert!(!is_distribution(vec![0.7, 0.2]));
+    assert!(!is_distribution(vec![1.5, -0.5]));
+    assert!(!is_distribution(vec![f64::NAN, 1.0]));
+    assert!(!is_distribution(Vec::new()));
+}